@@ -0,0 +1,227 @@
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Where and for how long API responses are kept on disk
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheConfig {
+    /// Responses are kept in a subdirectory per API base URL
+    pub dir: PathBuf,
+    /// How long train search responses stay fresh
+    pub ttl: Duration,
+    /// How long the station map stays fresh, stations change far less often than prices
+    pub stations_ttl: Duration,
+    /// Ignore cached responses, fresh ones are still stored
+    pub refresh: bool,
+}
+
+impl CacheConfig {
+    /// Cache under the user's cache directory (e.g. `$XDG_CACHE_HOME/eurostarchecker`)
+    pub fn in_user_cache_dir() -> Option<CacheConfig> {
+        Some(CacheConfig {
+            dir: dirs::cache_dir()?.join("eurostarchecker"),
+            ttl: Duration::from_secs(30 * 60),
+            stations_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+            refresh: false,
+        })
+    }
+}
+
+/// Which TTL applies to a response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CacheKind {
+    Stations,
+    Trains,
+}
+
+#[derive(Debug)]
+pub(crate) struct Cache {
+    config: CacheConfig,
+    /// Responses of each API server are kept apart, e.g. a staging server from production
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub(crate) fn new(config: CacheConfig, base_url: &str) -> Cache {
+        let dir = config.dir.join(sanitise(base_url));
+        Cache { config, dir }
+    }
+
+    pub(crate) fn get(
+        &self,
+        kind: CacheKind,
+        location: &str,
+        query_params: &HashMap<&str, String>,
+    ) -> Option<String> {
+        if self.config.refresh {
+            return None;
+        }
+
+        let path = self.path(location, query_params);
+        let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+        let ttl = match kind {
+            CacheKind::Stations => self.config.stations_ttl,
+            CacheKind::Trains => self.config.ttl,
+        };
+        // Modification time in the future counts as fresh
+        if SystemTime::now()
+            .duration_since(modified)
+            .map_or(false, |age| age > ttl)
+        {
+            debug!("Cached response {:?} is stale", path);
+            return None;
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(body) => {
+                debug!("Using cached response {:?}", path);
+                Some(body)
+            }
+            Err(err) => {
+                warn!("Failed to read cached response {:?}: {}", path, err);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn put(&self, location: &str, query_params: &HashMap<&str, String>, body: &str) {
+        let path = self.path(location, query_params);
+        if let Err(err) = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, body)) {
+            warn!("Failed to cache response in {:?}: {}", path, err);
+        }
+    }
+
+    fn path(&self, location: &str, query_params: &HashMap<&str, String>) -> PathBuf {
+        self.dir.join(file_name(location, query_params))
+    }
+}
+
+/// Route, dates and passengers are all part of the location or query, so they make the key
+pub(crate) fn file_name(location: &str, query_params: &HashMap<&str, String>) -> String {
+    let mut params: Vec<String> = query_params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    params.sort();
+
+    format!(
+        "{}.json",
+        sanitise(&format!("{}_{}", location, params.join("_")))
+    )
+}
+
+fn sanitise(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '=' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+    use std::process;
+
+    fn create_cache(name: &str, refresh: bool) -> Cache {
+        let dir = std::env::temp_dir().join(format!("eurostarchecker-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Cache::new(
+            CacheConfig {
+                dir,
+                ttl: Duration::from_secs(60),
+                stations_ttl: Duration::from_secs(0),
+                refresh,
+            },
+            "https://api.eurostar.com",
+        )
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let cache = create_cache("round-trip", false);
+        let params =
+            hashmap! {"outbound-date" => "2020-04-05".to_string(), "adult" => "2".to_string()};
+
+        assert_eq!(
+            cache.get(CacheKind::Trains, "train-search/uk-en/1/2", &params),
+            None
+        );
+        cache.put("train-search/uk-en/1/2", &params, "{}");
+
+        assert_eq!(
+            cache.get(CacheKind::Trains, "train-search/uk-en/1/2", &params),
+            Some("{}".to_string())
+        );
+        assert_eq!(
+            cache.path("train-search/uk-en/1/2", &params),
+            cache
+                .config
+                .dir
+                .join("https___api_eurostar_com")
+                .join("train-search_uk-en_1_2_adult=2_outbound-date=2020-04-05.json")
+        );
+        // Different passengers are a different search
+        let params =
+            hashmap! {"outbound-date" => "2020-04-05".to_string(), "adult" => "1".to_string()};
+        assert_eq!(
+            cache.get(CacheKind::Trains, "train-search/uk-en/1/2", &params),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cache_per_base_url() {
+        let cache = create_cache("base-url", false);
+        cache.put("hotels-search/regions/uk-en", &HashMap::new(), "{}");
+        let other = Cache::new(cache.config.clone(), "http://127.0.0.1:1234");
+
+        assert_eq!(
+            other.get(
+                CacheKind::Stations,
+                "hotels-search/regions/uk-en",
+                &HashMap::new()
+            ),
+            None
+        );
+        assert_eq!(other.dir, cache.config.dir.join("http___127_0_0_1_1234"));
+    }
+
+    #[test]
+    fn test_cache_stale() {
+        let cache = create_cache("stale", false);
+        cache.put("hotels-search/regions/uk-en", &HashMap::new(), "{}");
+        std::thread::sleep(Duration::from_millis(10));
+
+        assert_eq!(
+            cache.get(
+                CacheKind::Stations,
+                "hotels-search/regions/uk-en",
+                &HashMap::new()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cache_refresh() {
+        let cache = create_cache("refresh", true);
+        cache.put("hotels-search/regions/uk-en", &HashMap::new(), "{}");
+
+        assert_eq!(
+            cache.get(
+                CacheKind::Trains,
+                "hotels-search/regions/uk-en",
+                &HashMap::new()
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use std::io;
+
+use crate::client::SearchQuery;
+use crate::trains::{FareClass, TrainDetails, TrainJourney};
+
+static ICS_DATETIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+static ARRIVAL_FORMAT: &str = "%H:%M";
+/// Longest content line allowed by RFC 5545, longer ones are folded
+static MAX_LINE_OCTETS: usize = 75;
+
+/// Time zone of a departure station, train times are always local to the station
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TimeZone {
+    London,
+    Paris,
+    Brussels,
+    Amsterdam,
+}
+
+impl TimeZone {
+    fn of_station(station: &str) -> TimeZone {
+        match station.to_lowercase().as_str() {
+            "london" | "ebbsfleet" | "ashford" => TimeZone::London,
+            "brussels" => TimeZone::Brussels,
+            "amsterdam" | "rotterdam" => TimeZone::Amsterdam,
+            // Every other Eurostar station is in France
+            _ => TimeZone::Paris,
+        }
+    }
+
+    fn tzid(self) -> &'static str {
+        match self {
+            TimeZone::London => "Europe/London",
+            TimeZone::Paris => "Europe/Paris",
+            TimeZone::Brussels => "Europe/Brussels",
+            TimeZone::Amsterdam => "Europe/Amsterdam",
+        }
+    }
+
+    /// Names and UTC offsets of standard and summer time, they change on the same dates all over
+    /// the EU
+    fn offsets(self) -> ((&'static str, &'static str), (&'static str, &'static str)) {
+        match self {
+            TimeZone::London => (("GMT", "+0000"), ("BST", "+0100")),
+            _ => (("CET", "+0100"), ("CEST", "+0200")),
+        }
+    }
+
+    fn lines(self) -> Vec<String> {
+        let ((standard, standard_offset), (summer, summer_offset)) = self.offsets();
+        // Clocks change at 01:00 UTC, given in the local time before the change
+        let (summer_start, standard_start) = match self {
+            TimeZone::London => ("19700329T010000", "19701025T020000"),
+            _ => ("19700329T020000", "19701025T030000"),
+        };
+
+        vec![
+            "BEGIN:VTIMEZONE".to_string(),
+            format!("TZID:{}", self.tzid()),
+            "BEGIN:DAYLIGHT".to_string(),
+            format!("TZOFFSETFROM:{}", standard_offset),
+            format!("TZOFFSETTO:{}", summer_offset),
+            format!("TZNAME:{}", summer),
+            format!("DTSTART:{}", summer_start),
+            "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU".to_string(),
+            "END:DAYLIGHT".to_string(),
+            "BEGIN:STANDARD".to_string(),
+            format!("TZOFFSETFROM:{}", summer_offset),
+            format!("TZOFFSETTO:{}", standard_offset),
+            format!("TZNAME:{}", standard),
+            format!("DTSTART:{}", standard_start),
+            "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU".to_string(),
+            "END:STANDARD".to_string(),
+            "END:VTIMEZONE".to_string(),
+        ]
+    }
+}
+
+/// Leg of a journey, as an event in a calendar
+#[derive(PartialEq)]
+struct Leg<'a> {
+    /// Outbound or inbound, part of the event UID
+    direction: &'static str,
+    from: &'a str,
+    to: &'a str,
+    departure: NaiveDateTime,
+    arrival: NaiveDateTime,
+    duration: Duration,
+    details: &'a TrainDetails,
+    class: FareClass,
+}
+
+impl<'a> Leg<'a> {
+    fn lines(&self, stamp: DateTime<Utc>) -> Vec<String> {
+        let zone = TimeZone::of_station(self.from);
+        let connection = if self.details.direct {
+            ""
+        } else {
+            " (connection)"
+        };
+
+        vec![
+            "BEGIN:VEVENT".to_string(),
+            format!(
+                "UID:{}-{}-{}@eurostarchecker",
+                self.direction,
+                self.departure.format(ICS_DATETIME_FORMAT),
+                self.details.number
+            ),
+            format!("DTSTAMP:{}Z", stamp.format(ICS_DATETIME_FORMAT)),
+            format!(
+                "DTSTART;TZID={}:{}",
+                zone.tzid(),
+                self.departure.format(ICS_DATETIME_FORMAT)
+            ),
+            format!("DURATION:{}", format_duration(self.duration)),
+            format!(
+                "SUMMARY:{}",
+                escape(&format!(
+                    "Eurostar {}{} {} - {}",
+                    self.details.number, connection, self.from, self.to
+                ))
+            ),
+            format!(
+                "DESCRIPTION:{}",
+                escape(&format!(
+                    "{} class, arriving at {} local time",
+                    self.class,
+                    self.arrival.format(ARRIVAL_FORMAT)
+                ))
+            ),
+            "END:VEVENT".to_string(),
+        ]
+    }
+}
+
+/// iCalendar file with an event for the outbound and inbound leg of every journey, a train shared
+/// by several journeys is written once
+pub fn write_ics<W: io::Write>(
+    writer: W,
+    journeys: &[TrainJourney],
+    query: &SearchQuery,
+) -> io::Result<()> {
+    write_calendar(writer, journeys, query, Utc::now())
+}
+
+fn write_calendar<W: io::Write>(
+    mut writer: W,
+    journeys: &[TrainJourney],
+    query: &SearchQuery,
+    stamp: DateTime<Utc>,
+) -> io::Result<()> {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//eurostarchecker//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    let mut zones = Vec::new();
+    for station in [query.from.as_str(), query.return_from()].iter() {
+        let zone = TimeZone::of_station(station);
+        if !zones.contains(&zone) {
+            lines.append(&mut zone.lines());
+            zones.push(zone);
+        }
+    }
+
+    // The same outbound train pairs up with every inbound one of its dates
+    let mut legs: Vec<Leg> = Vec::new();
+    for journey in journeys.iter() {
+        let outbound = Leg {
+            direction: "outbound",
+            from: &query.from,
+            to: &query.to,
+            departure: journey.outbound,
+            arrival: journey.out_arrival,
+            duration: journey.out_duration,
+            details: &journey.out_details,
+            class: journey.out_class,
+        };
+        let inbound = Leg {
+            direction: "inbound",
+            from: query.return_from(),
+            to: query.return_to(),
+            departure: journey.inbound,
+            arrival: journey.in_arrival,
+            duration: journey.in_duration,
+            details: &journey.in_details,
+            class: journey.in_class,
+        };
+
+        for leg in vec![outbound, inbound].into_iter() {
+            if !legs.contains(&leg) {
+                legs.push(leg);
+            }
+        }
+    }
+    for leg in legs.iter() {
+        lines.append(&mut leg.lines(stamp));
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    for line in lines.iter() {
+        write!(writer, "{}\r\n", fold(line))?;
+    }
+    Ok(())
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!(
+        "PT{}H{}M",
+        duration.num_hours(),
+        duration.num_minutes() % 60
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits long lines, continuation lines start with a space
+fn fold(line: &str) -> String {
+    let mut res = String::new();
+    let mut octets = 0;
+
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            res.push_str("\r\n ");
+            // The leading space counts too
+            octets = 1;
+        }
+        res.push(c);
+        octets += c.len_utf8();
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date::StayLengths;
+    use crate::money::{Currency, Money};
+    use crate::trains::{ClassPreference, Fares, Filter, Passengers};
+    use chrono::{NaiveDate, TimeZone as _};
+
+    fn create_query() -> SearchQuery {
+        SearchQuery {
+            from: "London".to_string(),
+            to: "Paris".to_string(),
+            return_from: None,
+            return_to: None,
+            since: NaiveDate::from_ymd(2020, 4, 3),
+            until: NaiveDate::from_ymd(2020, 4, 10),
+            stay_lengths: StayLengths(vec![Duration::days(2)]),
+            weekdays: Vec::new(),
+            return_weekdays: Vec::new(),
+            passengers: Passengers {
+                adults: 1,
+                children: 0,
+                youths: 0,
+                seniors: 0,
+            },
+            class: ClassPreference::Only(FareClass::Standard),
+            filter: Filter::default(),
+        }
+    }
+
+    fn create_journey() -> TrainJourney {
+        let outbound = NaiveDate::from_ymd(2020, 4, 3).and_hms(18, 1, 0);
+        let inbound = NaiveDate::from_ymd(2020, 4, 5).and_hms(20, 13, 0);
+        let price = Money::new("78.5".parse().unwrap(), Currency::Gbp);
+
+        TrainJourney {
+            outbound,
+            inbound,
+            out_arrival: outbound + Duration::minutes(196),
+            in_arrival: inbound + Duration::minutes(76),
+            price,
+            fares: Fares {
+                adult: price,
+                ..Fares::zero(Currency::Gbp)
+            },
+            out_duration: Duration::minutes(136),
+            in_duration: Duration::minutes(136),
+            out_class: FareClass::Standard,
+            in_class: FareClass::Plus,
+            out_seats: Some(50),
+            in_seats: None,
+            out_details: TrainDetails {
+                number: "9051".to_string(),
+                direct: true,
+                customs_checks: false,
+                dob_required: false,
+            },
+            in_details: TrainDetails {
+                number: "9058".to_string(),
+                direct: false,
+                customs_checks: false,
+                dob_required: false,
+            },
+        }
+    }
+
+    #[test]
+    fn test_write_calendar() {
+        let mut output = Vec::new();
+        write_calendar(
+            &mut output,
+            &[create_journey()],
+            &create_query(),
+            Utc.ymd(2020, 4, 1).and_hms(12, 0, 0),
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with("END:VCALENDAR\r\n"));
+
+        let lines: Vec<&str> = output.split("\r\n").collect();
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with("TZID:"))
+                .collect::<Vec<_>>(),
+            vec![&"TZID:Europe/London", &"TZID:Europe/Paris"]
+        );
+
+        let events: Vec<&[&str]> = lines
+            .split(|line| *line == "BEGIN:VEVENT")
+            .skip(1)
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0][..7],
+            [
+                "UID:outbound-20200403T180100-9051@eurostarchecker",
+                "DTSTAMP:20200401T120000Z",
+                "DTSTART;TZID=Europe/London:20200403T180100",
+                "DURATION:PT2H16M",
+                "SUMMARY:Eurostar 9051 London - Paris",
+                "DESCRIPTION:Standard class\\, arriving at 21:17 local time",
+                "END:VEVENT",
+            ]
+        );
+        assert_eq!(
+            events[1][2..5],
+            [
+                "DTSTART;TZID=Europe/Paris:20200405T201300",
+                "DURATION:PT2H16M",
+                "SUMMARY:Eurostar 9058 (connection) Paris - London",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_calendar_shared_train() {
+        let later = create_journey().inbound + Duration::hours(2);
+        let journeys = vec![
+            create_journey(),
+            TrainJourney {
+                inbound: later,
+                in_arrival: later + Duration::minutes(76),
+                in_details: TrainDetails {
+                    number: "9062".to_string(),
+                    ..create_journey().in_details
+                },
+                ..create_journey()
+            },
+        ];
+
+        let mut output = Vec::new();
+        write_calendar(&mut output, &journeys, &create_query(), Utc::now()).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let uids: Vec<&str> = output
+            .split("\r\n")
+            .filter(|line| line.starts_with("UID:"))
+            .collect();
+        assert_eq!(
+            uids,
+            vec![
+                "UID:outbound-20200403T180100-9051@eurostarchecker",
+                "UID:inbound-20200405T201300-9058@eurostarchecker",
+                "UID:inbound-20200405T221300-9062@eurostarchecker",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        let line = format!("DESCRIPTION:{}", "é".repeat(40));
+        let folded = fold(&line);
+
+        assert_eq!(folded.replace("\r\n ", ""), line);
+        for line in folded.split("\r\n") {
+            assert!(line.len() <= MAX_LINE_OCTETS);
+        }
+        assert_eq!(fold("VERSION:2.0"), "VERSION:2.0");
+    }
+}
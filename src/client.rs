@@ -0,0 +1,379 @@
+use chrono::{NaiveDate, Weekday};
+use log::debug;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use crate::cache::CacheConfig;
+use crate::date::{self, StayLengths};
+use crate::money::Currency;
+use crate::recording::Recording;
+use crate::retry::RetryPolicy;
+use crate::throttle::RequestLimits;
+use crate::trains::{
+    get_journeys, get_one_way_trains, get_stations_map, Api, ClassPreference, Filter, Itinerary,
+    Passengers, QueryError, SearchResults, Train, TrainJourney,
+};
+
+/// Everything needed to search for trains, stations are given by their names
+#[derive(Debug)]
+pub struct SearchQuery {
+    pub from: String,
+    pub to: String,
+    /// Start station of the return journey, defaults to `to`
+    pub return_from: Option<String>,
+    /// Finish station of the return journey, defaults to `from`
+    pub return_to: Option<String>,
+    pub since: NaiveDate,
+    pub until: NaiveDate,
+    /// Ignored by one-way searches
+    pub stay_lengths: StayLengths,
+    /// Empty list means that any day of the week is fine
+    pub weekdays: Vec<Weekday>,
+    /// Ignored by one-way searches
+    pub return_weekdays: Vec<Weekday>,
+    pub passengers: Passengers,
+    pub class: ClassPreference,
+    pub filter: Filter,
+}
+
+impl SearchQuery {
+    pub fn return_from(&self) -> &str {
+        self.return_from.as_ref().unwrap_or(&self.to)
+    }
+
+    pub fn return_to(&self) -> &str {
+        self.return_to.as_ref().unwrap_or(&self.from)
+    }
+}
+
+/// Why a search couldn't be run, dates failing on their own are reported in `SearchResults`
+#[derive(Debug)]
+pub enum SearchError {
+    /// Query can't be answered as it is, e.g. unknown station or no matching dates
+    InvalidQuery(String),
+    /// Talking to the Eurostar API failed
+    QueryError(QueryError),
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchError::InvalidQuery(msg) => write!(f, "{}", msg),
+            SearchError::QueryError(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for SearchError {}
+
+impl From<QueryError> for SearchError {
+    fn from(err: QueryError) -> SearchError {
+        SearchError::QueryError(err)
+    }
+}
+
+/// Base URL of the production Eurostar API
+pub static EUROSTAR_URL: &str = "https://api.prod.eurostar.com/bpa";
+
+/// How the client talks to the Eurostar API
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Lets the client talk to a local stand-in instead of the real API
+    pub base_url: String,
+    /// Market decides about the currency and fares on offer, e.g. `uk`, `fr` or `be`
+    pub market: String,
+    /// Language of the market, e.g. `en`, `fr` or `nl`
+    pub language: String,
+    pub limits: RequestLimits,
+    pub retry: RetryPolicy,
+    /// Responses are not cached unless set
+    pub cache: Option<CacheConfig>,
+    pub recording: Option<Recording>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> ClientConfig {
+        ClientConfig {
+            base_url: EUROSTAR_URL.to_string(),
+            market: "uk".to_string(),
+            language: "en".to_string(),
+            limits: RequestLimits::default(),
+            retry: RetryPolicy::default(),
+            cache: None,
+            recording: None,
+        }
+    }
+}
+
+/// Entry point for searching Eurostar trains
+#[derive(Debug)]
+pub struct Client {
+    api: Api,
+}
+
+impl Client {
+    pub fn new(api_key: &str) -> Client {
+        Client::with_config(api_key, &ClientConfig::default())
+    }
+
+    pub fn with_config(api_key: &str, config: &ClientConfig) -> Client {
+        Client {
+            api: Api::new(api_key, config),
+        }
+    }
+
+    /// Map of station names to their IDs
+    pub async fn stations(&self) -> Result<HashMap<String, i32>, SearchError> {
+        Ok(get_stations_map(&self.api).await?)
+    }
+
+    /// Return journeys for every pair of dates matching the query, date pairs which failed are
+    /// reported along with journeys found for the others
+    pub async fn search(
+        &self,
+        query: &SearchQuery,
+    ) -> Result<SearchResults<TrainJourney>, SearchError> {
+        validate_stations(&query.from, &query.to)?;
+        validate_stations(query.return_from(), query.return_to())?;
+        validate_max_price(&query.filter, self.api.currency())?;
+
+        let travels = date::get_possible_travel_dates(
+            query.since,
+            query.until,
+            &query.stay_lengths.0,
+            &query.weekdays,
+            &query.return_weekdays,
+        )
+        .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+        debug!("Possible travel dates: {:#?}", travels);
+
+        let stations = self.stations().await?;
+        let itinerary = Itinerary {
+            from: parse_station(&query.from, &stations)?,
+            to: parse_station(&query.to, &stations)?,
+            return_from: parse_station(query.return_from(), &stations)?,
+            return_to: parse_station(query.return_to(), &stations)?,
+        };
+
+        Ok(get_journeys(
+            &travels,
+            &self.api,
+            &itinerary,
+            &query.passengers,
+            query.class,
+            &query.filter,
+        )
+        .await)
+    }
+
+    /// Outbound trains only, for every date matching the query
+    pub async fn search_one_way(
+        &self,
+        query: &SearchQuery,
+    ) -> Result<SearchResults<Train>, SearchError> {
+        validate_stations(&query.from, &query.to)?;
+        validate_max_price(&query.filter, self.api.currency())?;
+
+        let dates = date::get_possible_outbound_dates(query.since, query.until, &query.weekdays)
+            .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+        debug!("Possible travel dates: {:#?}", dates);
+
+        let stations = self.stations().await?;
+
+        Ok(get_one_way_trains(
+            &dates,
+            &self.api,
+            parse_station(&query.from, &stations)?,
+            parse_station(&query.to, &stations)?,
+            &query.passengers,
+            query.class,
+            &query.filter,
+        )
+        .await)
+    }
+}
+
+fn validate_stations(from: &str, to: &str) -> Result<(), SearchError> {
+    if from == to {
+        return Err(SearchError::InvalidQuery(
+            "Start and finish stations need to be different!".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_max_price(filter: &Filter, currency: Currency) -> Result<(), SearchError> {
+    match filter.max_price {
+        Some(max_price) if max_price.currency != currency => {
+            Err(SearchError::InvalidQuery(format!(
+                "Max price is given in {} but the market prices in {}!",
+                max_price.currency, currency
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn parse_station(name: &str, station_map: &HashMap<String, i32>) -> Result<i32, SearchError> {
+    match station_map.get(name) {
+        Some(res) => Ok(*res),
+        None => {
+            let mut keys: Vec<&str> = station_map.keys().map(|val| val.as_str()).collect();
+            keys.sort();
+            Err(SearchError::InvalidQuery(format!(
+                "'{}' is invalid city name, choose from: {}.",
+                name,
+                keys.join(", ")
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Money;
+    use chrono::Duration;
+    use mockito::{mock, Matcher};
+    use rust_decimal::Decimal;
+
+    static API_KEY: &str = "api-key";
+
+    fn client() -> Client {
+        Client::with_config(
+            API_KEY,
+            &ClientConfig {
+                base_url: mockito::server_url(),
+                ..ClientConfig::default()
+            },
+        )
+    }
+
+    fn create_query(from: &str, to: &str) -> SearchQuery {
+        SearchQuery {
+            from: from.to_string(),
+            to: to.to_string(),
+            return_from: None,
+            return_to: None,
+            since: NaiveDate::from_ymd(2020, 4, 5),
+            until: NaiveDate::from_ymd(2020, 4, 7),
+            stay_lengths: StayLengths(vec![Duration::days(2)]),
+            weekdays: Vec::new(),
+            return_weekdays: Vec::new(),
+            passengers: Passengers {
+                adults: 1,
+                children: 0,
+                youths: 0,
+                seniors: 0,
+            },
+            class: ClassPreference::AnyCheapest,
+            filter: Filter::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_resolves_station_names() {
+        let _stations = mock("GET", "/hotels-search/regions/uk-en")
+            .with_status(200)
+            .with_body(include_str!("test_resources/stations.json"))
+            .create();
+        let _trains = mock("GET", "/train-search/uk-en/7015400/7054660")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("outbound-date".into(), "2020-04-05".into()),
+                Matcher::UrlEncoded("inbound-date".into(), "2020-04-07".into()),
+            ]))
+            .with_status(200)
+            .with_body(include_str!("test_resources/response.json"))
+            .create();
+
+        let journeys = client()
+            .search(&create_query("London", "Ashford"))
+            .await
+            .unwrap();
+
+        assert!(journeys.failures.is_empty());
+        let journeys = journeys.results;
+        assert!(!journeys.is_empty());
+        assert!(journeys
+            .iter()
+            .all(|journey| journey.outbound.date() == NaiveDate::from_ymd(2020, 4, 5)));
+    }
+
+    #[tokio::test]
+    async fn test_search_market() {
+        let _stations = mock("GET", "/hotels-search/regions/fr-fr")
+            .with_status(200)
+            .with_body(include_str!("test_resources/stations.json"))
+            .create();
+        let trains = mock("GET", "/train-search/fr-fr/7015400/7054660")
+            .match_query(Matcher::UrlEncoded(
+                "outbound-date".into(),
+                "2020-04-05".into(),
+            ))
+            .with_status(200)
+            .with_body(include_str!("test_resources/response.json"))
+            .create();
+        let client = Client::with_config(
+            API_KEY,
+            &ClientConfig {
+                base_url: format!("{}/", mockito::server_url()),
+                market: "fr".to_string(),
+                language: "fr".to_string(),
+                ..ClientConfig::default()
+            },
+        );
+
+        let mut query = create_query("London", "Ashford");
+        query.until = query.since;
+        let trains_found = client.search_one_way(&query).await.unwrap();
+
+        trains.assert();
+        assert!(trains_found.failures.is_empty());
+        assert!(!trains_found.results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_unknown_station() {
+        let _stations = mock("GET", "/hotels-search/regions/uk-en")
+            .with_status(200)
+            .with_body(include_str!("test_resources/stations.json"))
+            .create();
+
+        match client()
+            .search_one_way(&create_query("London", "Atlantis"))
+            .await
+        {
+            Err(SearchError::InvalidQuery(msg)) => assert_eq!(
+                msg,
+                "'Atlantis' is invalid city name, choose from: Ashford, London."
+            ),
+            res => panic!("search_one_way returned: {:?}", res),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_max_price_currency() {
+        let mut query = create_query("London", "Ashford");
+        query.filter.max_price = Some(Money::new(Decimal::from(100), Currency::Eur));
+
+        match client().search(&query).await {
+            Err(SearchError::InvalidQuery(msg)) => assert_eq!(
+                msg,
+                "Max price is given in EUR but the market prices in GBP!"
+            ),
+            res => panic!("search returned: {:?}", res),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_same_stations() {
+        let mut query = create_query("London", "Ashford");
+        query.return_from = Some("London".to_string());
+
+        match client().search(&query).await {
+            Err(SearchError::InvalidQuery(_)) => (),
+            res => panic!("search returned: {:?}", res),
+        }
+    }
+}
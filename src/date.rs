@@ -1,9 +1,9 @@
-use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
 use std::fmt;
 use std::num::ParseIntError;
 
-static USER_FORMAT: &'static str = "%Y-%m-%d";
-static TIME_FORMAT: &'static str = "%H:%M";
+static USER_FORMAT: &str = "%Y-%m-%d";
+static TIME_FORMAT: &str = "%H:%M";
 pub static NOW: &str = "now";
 pub static PLUS_TWO_WEEKS: &str = "+2 weeks";
 
@@ -12,6 +12,7 @@ pub enum ParseError {
     ChronoError(chrono::format::ParseError),
     DateInThePastError(String),
     InvalidWeekday(String),
+    InvalidRange(String),
     ParseIntError(ParseIntError),
 }
 
@@ -19,11 +20,18 @@ impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ParseError::ChronoError(err) => write!(f, "{}", err),
-            msg => write!(f, "{}", msg),
+            ParseError::ParseIntError(err) => write!(f, "{}", err),
+            ParseError::DateInThePastError(msg)
+            | ParseError::InvalidWeekday(msg)
+            | ParseError::InvalidRange(msg) => write!(f, "{}", msg),
         }
     }
 }
 
+/// Stay lengths to consider, each stored as an offset between outbound and inbound date.
+#[derive(Debug, PartialEq, Default)]
+pub struct StayLengths(pub Vec<Duration>);
+
 pub mod naive_time {
     use super::TIME_FORMAT;
     use chrono::NaiveTime;
@@ -66,20 +74,54 @@ pub fn parse_duration_from_str(days: &str) -> Result<Duration, ParseError> {
     }
 }
 
+pub fn parse_stay_lengths_from_str(days: &str) -> Result<StayLengths, ParseError> {
+    let mut lengths = match days.find("..") {
+        Some(idx) => {
+            let start = parse_duration_from_str(&days[..idx])?;
+            let end = parse_duration_from_str(&days[idx + 2..])?;
+            if start > end {
+                return Err(ParseError::InvalidRange(format!(
+                    "{} is an invalid range of days!",
+                    days
+                )));
+            }
+            (start.num_days()..=end.num_days())
+                .map(Duration::days)
+                .collect()
+        }
+        None => days
+            .split(',')
+            .map(parse_duration_from_str)
+            .collect::<Result<Vec<Duration>, ParseError>>()?,
+    };
+    lengths.sort();
+    lengths.dedup();
+
+    Ok(StayLengths(lengths))
+}
+
 pub fn parse_date_from_str(date: &str) -> Result<NaiveDate, ParseError> {
+    ensure_not_in_past(parse_any_date_from_str(date)?)
+}
+
+/// Same as `parse_date_from_str`, but dates in the past are fine too
+pub fn parse_any_date_from_str(date: &str) -> Result<NaiveDate, ParseError> {
     if date == NOW {
         return Ok(Utc::today().naive_local());
     } else if date == PLUS_TWO_WEEKS {
         return Ok(Utc::today().naive_local() + Duration::weeks(2));
     }
-    let parsed = NaiveDate::parse_from_str(date, USER_FORMAT).map_err(ParseError::ChronoError)?;
-    if parsed < Utc::today().naive_local() {
+    NaiveDate::parse_from_str(date, USER_FORMAT).map_err(ParseError::ChronoError)
+}
+
+pub fn ensure_not_in_past(date: NaiveDate) -> Result<NaiveDate, ParseError> {
+    if date < Utc::today().naive_local() {
         return Err(ParseError::DateInThePastError(format!(
             "{:?} is in the past!",
-            parsed
+            date
         )));
     }
-    Ok(parsed)
+    Ok(date)
 }
 
 pub fn parse_hour_from_str(time: &str) -> Result<NaiveTime, ParseError> {
@@ -88,13 +130,13 @@ pub fn parse_hour_from_str(time: &str) -> Result<NaiveTime, ParseError> {
 
 pub fn parse_weekday_from_str(weekday: &str) -> Result<Weekday, ParseError> {
     match weekday.to_lowercase().as_str() {
-        "monday" => Ok(Weekday::Mon),
-        "tuesday" => Ok(Weekday::Tue),
-        "wednesday" => Ok(Weekday::Wed),
-        "thursday" => Ok(Weekday::Thu),
-        "friday" => Ok(Weekday::Fri),
-        "saturday" => Ok(Weekday::Sat),
-        "sunday" => Ok(Weekday::Sun),
+        "monday" | "mon" => Ok(Weekday::Mon),
+        "tuesday" | "tue" => Ok(Weekday::Tue),
+        "wednesday" | "wed" => Ok(Weekday::Wed),
+        "thursday" | "thu" => Ok(Weekday::Thu),
+        "friday" | "fri" => Ok(Weekday::Fri),
+        "saturday" | "sat" => Ok(Weekday::Sat),
+        "sunday" | "sun" => Ok(Weekday::Sun),
         day => Err(ParseError::InvalidWeekday(format!(
             "{} is an invalid weekday name!",
             day
@@ -105,46 +147,95 @@ pub fn parse_weekday_from_str(weekday: &str) -> Result<Weekday, ParseError> {
 pub fn get_possible_travel_dates(
     from: NaiveDate,
     until: NaiveDate,
-    days: Duration,
-    weekday: Option<Weekday>,
+    days: &[Duration],
+    weekdays: &[Weekday],
+    return_weekdays: &[Weekday],
 ) -> Result<Vec<(NaiveDate, NaiveDate)>, &'static str> {
     let mut results = Vec::new();
+    let shortest = match days.iter().min() {
+        Some(shortest) => *shortest,
+        None => return Err("At least one stay length is required"),
+    };
 
-    let mut outbound = match weekday {
-        Some(weekday) => {
-            let mut day = from;
-            while day.weekday() != weekday {
-                day = day.succ();
+    for outbound in get_outbound_dates(from, until - shortest, weekdays) {
+        for stay in days.iter() {
+            let inbound = outbound + *stay;
+            if inbound <= until && matches_weekdays(inbound, return_weekdays) {
+                results.push((outbound, inbound));
             }
-            day
         }
-        None => from,
-    };
-    let mut inbound = outbound + days;
+    }
 
-    if inbound > until {
+    if results.is_empty() {
         return Err("There is no possible out-inbound dates which could satisfy your query");
-    };
+    }
 
-    while inbound <= until {
-        results.push((outbound, inbound));
-        outbound = match weekday {
-            Some(_) => outbound + Duration::days(7),
-            None => outbound.succ(),
-        };
-        inbound = outbound + days;
+    Ok(results)
+}
+
+pub fn get_possible_outbound_dates(
+    from: NaiveDate,
+    until: NaiveDate,
+    weekdays: &[Weekday],
+) -> Result<Vec<NaiveDate>, &'static str> {
+    let results = get_outbound_dates(from, until, weekdays);
+
+    if results.is_empty() {
+        return Err("There is no possible outbound dates which could satisfy your query");
     }
 
     Ok(results)
 }
 
+fn get_outbound_dates(from: NaiveDate, until: NaiveDate, weekdays: &[Weekday]) -> Vec<NaiveDate> {
+    let mut results = Vec::new();
+    let mut outbound = from;
+
+    while outbound <= until {
+        if matches_weekdays(outbound, weekdays) {
+            results.push(outbound);
+        }
+        outbound = outbound.succ();
+    }
+
+    results
+}
+
+/// Empty list of weekdays means that any day of the week is fine
+fn matches_weekdays(date: NaiveDate, weekdays: &[Weekday]) -> bool {
+    weekdays.is_empty() || weekdays.contains(&date.weekday())
+}
+
+/// Arrival time is given in local time of the destination, which may be in a different timezone
+/// than the origin, so pick the arrival date which lands closest to the departure plus duration.
+pub fn get_arrival_datetime(
+    departure: NaiveDateTime,
+    duration: Duration,
+    arrival_time: NaiveTime,
+) -> NaiveDateTime {
+    let expected = departure + duration;
+    let arrival = NaiveDateTime::new(expected.date(), arrival_time);
+
+    [
+        arrival - Duration::days(1),
+        arrival,
+        arrival + Duration::days(1),
+    ]
+    .iter()
+    .min_by_key(|candidate| (**candidate - expected).num_minutes().abs())
+    .copied()
+    .unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::date::{
-        get_possible_travel_dates, parse_date_from_str, parse_duration_from_str,
-        parse_hour_from_str, parse_weekday_from_str, ParseError, NOW, PLUS_TWO_WEEKS,
+        get_arrival_datetime, get_possible_outbound_dates, get_possible_travel_dates,
+        parse_date_from_str, parse_duration_from_str, parse_hour_from_str,
+        parse_stay_lengths_from_str, parse_weekday_from_str, ParseError, StayLengths, NOW,
+        PLUS_TWO_WEEKS,
     };
-    use chrono::{Duration, NaiveDate, NaiveTime, Utc, Weekday};
+    use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
 
     #[test]
     fn test_duration_from_str() {
@@ -167,6 +258,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_stay_lengths_from_str() {
+        let cases = [
+            ("3", vec![Duration::days(2)]),
+            (
+                "2..4",
+                vec![Duration::days(1), Duration::days(2), Duration::days(3)],
+            ),
+            (
+                "5,2,3,2",
+                vec![Duration::days(1), Duration::days(2), Duration::days(4)],
+            ),
+        ];
+
+        for (string, lengths) in cases.iter() {
+            assert_eq!(
+                parse_stay_lengths_from_str(string).unwrap(),
+                StayLengths(lengths.clone())
+            )
+        }
+    }
+
+    #[test]
+    fn test_parse_stay_lengths_from_str_invalid() {
+        assert_eq!(
+            parse_stay_lengths_from_str("4..2").unwrap_err(),
+            ParseError::InvalidRange("4..2 is an invalid range of days!".to_string()),
+        );
+        assert_eq!(
+            parse_stay_lengths_from_str("2,0").unwrap_err(),
+            ParseError::DateInThePastError("Number of days must be greater than 0!".to_string()),
+        );
+        assert!(parse_stay_lengths_from_str("2..").is_err());
+    }
+
     #[test]
     fn test_parse_date_from_str_now() {
         assert_eq!(
@@ -228,7 +354,7 @@ mod tests {
 
     #[test]
     fn test_parse_weekday_from_str() {
-        let cases = vec![
+        let cases = [
             ("monday", Weekday::Mon),
             ("Tuesday", Weekday::Tue),
             ("WEDNESDAY", Weekday::Wed),
@@ -236,6 +362,8 @@ mod tests {
             ("friday", Weekday::Fri),
             ("Saturday", Weekday::Sat),
             ("SUNDAY", Weekday::Sun),
+            ("fri", Weekday::Fri),
+            ("Sat", Weekday::Sat),
         ];
 
         for (string, weekday) in cases.iter() {
@@ -254,8 +382,9 @@ mod tests {
         match get_possible_travel_dates(
             NaiveDate::from_ymd(2020, 1, 1),
             NaiveDate::from_ymd(2020, 1, 3),
-            Duration::days(3),
-            None,
+            &[Duration::days(3)],
+            &[],
+            &[],
         ) {
             Err(err) => assert_eq!(err, msg),
             _ => panic!("get_possible_travel_dates should have return error!"),
@@ -264,8 +393,9 @@ mod tests {
         match get_possible_travel_dates(
             NaiveDate::from_ymd(2020, 4, 1),
             NaiveDate::from_ymd(2020, 4, 10),
-            Duration::days(6),
-            Some(Weekday::Tue),
+            &[Duration::days(6)],
+            &[Weekday::Tue],
+            &[],
         ) {
             Err(err) => assert_eq!(err, msg),
             _ => panic!("get_possible_travel_dates should have return error!"),
@@ -278,8 +408,9 @@ mod tests {
             get_possible_travel_dates(
                 NaiveDate::from_ymd(2020, 1, 1),
                 NaiveDate::from_ymd(2020, 1, 6),
-                Duration::days(3),
-                None,
+                &[Duration::days(3)],
+                &[],
+                &[],
             )
             .unwrap(),
             vec![
@@ -302,8 +433,9 @@ mod tests {
             get_possible_travel_dates(
                 NaiveDate::from_ymd(2020, 4, 1),
                 NaiveDate::from_ymd(2020, 4, 6),
-                Duration::days(3),
-                Some(Weekday::Wed),
+                &[Duration::days(3)],
+                &[Weekday::Wed],
+                &[],
             )
             .unwrap(),
             vec![(
@@ -312,4 +444,159 @@ mod tests {
             ),]
         )
     }
+
+    #[test]
+    fn test_get_possible_travel_dates_multiple_stay_lengths() {
+        assert_eq!(
+            get_possible_travel_dates(
+                NaiveDate::from_ymd(2020, 4, 1),
+                NaiveDate::from_ymd(2020, 4, 4),
+                &[Duration::days(1), Duration::days(2)],
+                &[],
+                &[],
+            )
+            .unwrap(),
+            vec![
+                (
+                    NaiveDate::from_ymd(2020, 4, 1),
+                    NaiveDate::from_ymd(2020, 4, 2)
+                ),
+                (
+                    NaiveDate::from_ymd(2020, 4, 1),
+                    NaiveDate::from_ymd(2020, 4, 3)
+                ),
+                (
+                    NaiveDate::from_ymd(2020, 4, 2),
+                    NaiveDate::from_ymd(2020, 4, 3)
+                ),
+                (
+                    NaiveDate::from_ymd(2020, 4, 2),
+                    NaiveDate::from_ymd(2020, 4, 4)
+                ),
+                (
+                    NaiveDate::from_ymd(2020, 4, 3),
+                    NaiveDate::from_ymd(2020, 4, 4)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_possible_travel_dates_weekdays() {
+        // Fri/Sat departures returning on Sun/Mon, staying 2 to 4 days
+        assert_eq!(
+            get_possible_travel_dates(
+                NaiveDate::from_ymd(2020, 4, 1),
+                NaiveDate::from_ymd(2020, 4, 10),
+                &[Duration::days(1), Duration::days(2), Duration::days(3)],
+                &[Weekday::Fri, Weekday::Sat],
+                &[Weekday::Sun, Weekday::Mon],
+            )
+            .unwrap(),
+            vec![
+                (
+                    NaiveDate::from_ymd(2020, 4, 3),
+                    NaiveDate::from_ymd(2020, 4, 5)
+                ),
+                (
+                    NaiveDate::from_ymd(2020, 4, 3),
+                    NaiveDate::from_ymd(2020, 4, 6)
+                ),
+                (
+                    NaiveDate::from_ymd(2020, 4, 4),
+                    NaiveDate::from_ymd(2020, 4, 5)
+                ),
+                (
+                    NaiveDate::from_ymd(2020, 4, 4),
+                    NaiveDate::from_ymd(2020, 4, 6)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_possible_outbound_dates() {
+        assert_eq!(
+            get_possible_outbound_dates(
+                NaiveDate::from_ymd(2020, 4, 1),
+                NaiveDate::from_ymd(2020, 4, 3),
+                &[],
+            )
+            .unwrap(),
+            vec![
+                NaiveDate::from_ymd(2020, 4, 1),
+                NaiveDate::from_ymd(2020, 4, 2),
+                NaiveDate::from_ymd(2020, 4, 3),
+            ]
+        );
+
+        assert_eq!(
+            get_possible_outbound_dates(
+                NaiveDate::from_ymd(2020, 4, 1),
+                NaiveDate::from_ymd(2020, 4, 15),
+                &[Weekday::Fri],
+            )
+            .unwrap(),
+            vec![
+                NaiveDate::from_ymd(2020, 4, 3),
+                NaiveDate::from_ymd(2020, 4, 10),
+            ]
+        );
+
+        assert_eq!(
+            get_possible_outbound_dates(
+                NaiveDate::from_ymd(2020, 4, 1),
+                NaiveDate::from_ymd(2020, 4, 2),
+                &[Weekday::Fri],
+            )
+            .unwrap_err(),
+            "There is no possible outbound dates which could satisfy your query"
+        );
+    }
+
+    #[test]
+    fn test_get_arrival_datetime() {
+        let day = NaiveDate::from_ymd(2020, 4, 3);
+        let cases = [
+            // London -> Paris, an hour ahead
+            (
+                NaiveTime::from_hms(5, 40, 0),
+                157,
+                NaiveTime::from_hms(9, 17, 0),
+                NaiveDateTime::new(day, NaiveTime::from_hms(9, 17, 0)),
+            ),
+            // Paris -> London, an hour behind
+            (
+                NaiveTime::from_hms(6, 33, 0),
+                149,
+                NaiveTime::from_hms(8, 2, 0),
+                NaiveDateTime::new(day, NaiveTime::from_hms(8, 2, 0)),
+            ),
+            // Overnight crossing
+            (
+                NaiveTime::from_hms(22, 30, 0),
+                150,
+                NaiveTime::from_hms(2, 0, 0),
+                NaiveDateTime::new(day.succ(), NaiveTime::from_hms(2, 0, 0)),
+            ),
+            // Late departure arriving just before midnight thanks to timezone shift
+            (
+                NaiveTime::from_hms(22, 30, 0),
+                120,
+                NaiveTime::from_hms(23, 30, 0),
+                NaiveDateTime::new(day, NaiveTime::from_hms(23, 30, 0)),
+            ),
+        ];
+
+        for (departure, duration, arrival, expected) in cases.iter() {
+            assert_eq!(
+                get_arrival_datetime(
+                    NaiveDateTime::new(day, *departure),
+                    Duration::minutes(*duration),
+                    *arrival
+                ),
+                *expected
+            );
+        }
+    }
 }
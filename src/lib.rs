@@ -0,0 +1,74 @@
+//! Search for Eurostar journeys matching supplied criteria.
+//!
+//! ```no_run
+//! use chrono::NaiveDate;
+//! use eurostarchecker::{
+//!     date, Client, ClassPreference, Currency, Filter, FareClass, Money, Passengers, SearchQuery,
+//! };
+//! use rust_decimal::Decimal;
+//!
+//! # async fn run() -> Result<(), eurostarchecker::SearchError> {
+//! let client = Client::new("api-key");
+//! let query = SearchQuery {
+//!     from: "London".to_string(),
+//!     to: "Paris".to_string(),
+//!     return_from: None,
+//!     return_to: None,
+//!     since: NaiveDate::from_ymd(2020, 6, 1),
+//!     until: NaiveDate::from_ymd(2020, 6, 30),
+//!     stay_lengths: date::parse_stay_lengths_from_str("2..4").unwrap(),
+//!     weekdays: Vec::new(),
+//!     return_weekdays: Vec::new(),
+//!     passengers: Passengers { adults: 2, children: 0, youths: 0, seniors: 0 },
+//!     class: ClassPreference::Only(FareClass::Standard),
+//!     filter: Filter {
+//!         max_price: Some(Money::new(Decimal::from(200), Currency::Gbp)),
+//!         ..Filter::default()
+//!     },
+//! };
+//!
+//! let journeys = client.search(&query).await?;
+//! for journey in journeys.results {
+//!     println!("{} - {}: {}", journey.outbound, journey.inbound, journey.price);
+//! }
+//! for failure in journeys.failures {
+//!     eprintln!("{}", failure);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod cache;
+mod calendar;
+mod client;
+pub mod date;
+mod market;
+mod money;
+mod output;
+mod rates;
+mod recording;
+mod report;
+mod retry;
+mod table;
+mod throttle;
+mod trains;
+
+pub use cache::CacheConfig;
+pub use calendar::write_ics;
+pub use client::{Client, ClientConfig, SearchError, SearchQuery, EUROSTAR_URL};
+pub use market::{compare_markets, Market, MarketJourney};
+pub use money::{Currency, Money};
+pub use output::{
+    sort_journeys, sort_trains, write_json, write_market_json, write_one_way_json, Formatter,
+    Formatters, JsonFormatter, OutputContext, SortBy, JSON_VERSION,
+};
+pub use rates::{exchange_rate, ExchangeRates, RatesError};
+pub use recording::Recording;
+pub use report::{HtmlFormatter, MarkdownFormatter};
+pub use retry::RetryPolicy;
+pub use table::{DelimitedFormatter, TableFormatter};
+pub use throttle::RequestLimits;
+pub use trains::{
+    ClassPreference, DateFailure, FareClass, Fares, Filter, Passengers, QueryError, SearchResults,
+    Train, TrainDetails, TrainJourney,
+};
@@ -1,26 +1,21 @@
 extern crate structopt;
 
-use chrono::{Duration, NaiveDate, NaiveTime, Weekday};
-use clap::arg_enum;
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use eurostarchecker::{
+    compare_markets, date, exchange_rate, sort_journeys, sort_trains, write_ics, CacheConfig,
+    ClassPreference, Client, ClientConfig, Currency, DateFailure, ExchangeRates, Filter,
+    Formatters, Market, Money, OutputContext, Passengers, Recording, RequestLimits, RetryPolicy,
+    SearchError, SearchQuery, SortBy, TrainJourney, EUROSTAR_URL,
+};
 use log::{debug, error, info};
-use prettytable::{cell, format, row, Table};
-use std::collections::HashMap;
-use stderrlog;
+use rust_decimal::Decimal;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration as StdDuration;
 use structopt::{clap, StructOpt};
-use tokio;
-mod date;
-mod trains;
-use trains::{get_journeys, get_stations_map, Filter, TrainJourney};
-
-static RESULT_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M";
-
-arg_enum! {
-    #[derive(Debug)]
-    enum SortBy {
-        Price,
-        Date,
-    }
-}
+
+static PARTIAL_RESULTS_EXIT_CODE: i32 = 2;
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "eurostarchecker")]
@@ -30,20 +25,28 @@ struct Opt {
     verbose: usize,
 
     /// Since what date we should look
-    #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = date::parse_date_from_str), default_value=date::NOW)]
+    #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = date::parse_any_date_from_str), default_value=date::NOW)]
     since: NaiveDate,
 
     /// To what date we should look
-    #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = date::parse_date_from_str), default_value=date::PLUS_TWO_WEEKS)]
+    #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = date::parse_any_date_from_str), default_value=date::PLUS_TWO_WEEKS)]
     until: NaiveDate,
 
-    /// Number of days to stay (e.g. Friday - Sunday would be 3 days)
-    #[structopt(short, long, parse(try_from_str = date::parse_duration_from_str))]
-    days: Duration,
+    /// Number of days to stay (e.g. Friday - Sunday would be 3 days), accepts a range (2..4) or a list (2,3,5)
+    #[structopt(short, long, required_unless = "one-way", parse(try_from_str = date::parse_stay_lengths_from_str))]
+    days: Option<date::StayLengths>,
+
+    /// Only look for outbound trains, without a return journey
+    #[structopt(long, conflicts_with_all = &["days", "in-departure-after", "in-departure-before", "in-arrive-after", "in-arrive-before"])]
+    one_way: bool,
+
+    /// Which days of the week should be considered as a start of a journey (e.g. fri,sat)
+    #[structopt(short, long, require_delimiter = true, parse(try_from_str = date::parse_weekday_from_str))]
+    weekday: Vec<Weekday>,
 
-    /// Which days of the week should be considered as a start of a journey
-    #[structopt(short, long, parse(try_from_str = date::parse_weekday_from_str))]
-    weekday: Option<Weekday>,
+    /// Which days of the week should be considered as an end of a journey (e.g. sun,mon)
+    #[structopt(long, require_delimiter = true, conflicts_with = "one-way", parse(try_from_str = date::parse_weekday_from_str))]
+    return_weekday: Vec<Weekday>,
 
     /// Only consider outbound trains departing after this time
     #[structopt(long, value_name = "HH:MM", parse(try_from_str = date::parse_hour_from_str))]
@@ -61,22 +64,153 @@ struct Opt {
     #[structopt(long, value_name = "HH:MM", parse(try_from_str = date::parse_hour_from_str))]
     in_departure_before: Option<NaiveTime>,
 
-    /// Max price per journey
+    /// Only consider outbound trains arriving after this time (local time at destination)
+    #[structopt(long, value_name = "HH:MM", parse(try_from_str = date::parse_hour_from_str))]
+    out_arrive_after: Option<NaiveTime>,
+
+    /// Only consider outbound trains arriving before this time (local time at destination)
+    #[structopt(long, value_name = "HH:MM", parse(try_from_str = date::parse_hour_from_str))]
+    out_arrive_before: Option<NaiveTime>,
+
+    /// Only consider inbound trains arriving after this time (local time at destination)
+    #[structopt(long, value_name = "HH:MM", parse(try_from_str = date::parse_hour_from_str))]
+    in_arrive_after: Option<NaiveTime>,
+
+    /// Only consider inbound trains arriving before this time (local time at destination)
+    #[structopt(long, value_name = "HH:MM", parse(try_from_str = date::parse_hour_from_str))]
+    in_arrive_before: Option<NaiveTime>,
+
+    /// Max price per journey for all passengers, in the currency of the market
     #[structopt(short, long)]
-    max_price: Option<f32>,
+    max_price: Option<Decimal>,
+
+    /// Only consider trains with at least this many seats left, defaults to number of passengers
+    #[structopt(long)]
+    min_seats: Option<i32>,
+
+    /// Only consider direct trains, without connections (e.g. via Lille or Brussels)
+    #[structopt(long)]
+    direct_only: bool,
 
     /// Eurostar API key
-    #[structopt(short, long)]
-    api_key: String,
+    #[structopt(short, long, required_unless = "replay")]
+    api_key: Option<String>,
+
+    /// Base URL of the Eurostar API
+    #[structopt(
+        long,
+        value_name = "URL",
+        default_value = EUROSTAR_URL
+    )]
+    api_url: String,
+
+    /// Which market to search in, it decides about currency and fares (e.g. uk, fr, be)
+    #[structopt(long, default_value = "uk")]
+    market: String,
+
+    /// Language used with the market (e.g. en, fr, nl)
+    #[structopt(long, default_value = "en")]
+    language: String,
+
+    /// Compare prices in these markets and show the cheapest one for every journey (e.g.
+    /// uk-en,fr-fr,be-fr), prices are given in the currency of the first one unless
+    /// --display-currency is set
+    #[structopt(long, require_delimiter = true, conflicts_with_all = &["one-way", "market", "language"])]
+    markets: Vec<Market>,
+
+    /// Show prices in this currency (GBP or EUR), --max-price and sorting use it too
+    #[structopt(long, value_name = "CURRENCY")]
+    display_currency: Option<Currency>,
+
+    /// JSON or TOML file with exchange rates used to convert prices, e.g. {"date": "2020-04-01",
+    /// "base": "EUR", "rates": {"GBP": 0.88}}
+    #[structopt(long, value_name = "FILE", parse(from_os_str))]
+    rates: Option<PathBuf>,
+
+    /// Store raw API responses in this directory, so the search can be replayed later
+    #[structopt(long, value_name = "DIR", parse(from_os_str))]
+    record: Option<PathBuf>,
+
+    /// Serve API responses from a directory filled by --record instead of the network
+    #[structopt(
+        long,
+        value_name = "DIR",
+        parse(from_os_str),
+        conflicts_with = "record"
+    )]
+    replay: Option<PathBuf>,
+
+    /// How many requests to the Eurostar API may be in flight at the same time
+    #[structopt(long, value_name = "N", default_value = "4")]
+    max_concurrent_requests: usize,
+
+    /// How many requests to the Eurostar API may be started per second, 0 means no limit
+    #[structopt(long, value_name = "N", default_value = "5")]
+    requests_per_second: f32,
+
+    /// Don't read or store cached API responses
+    #[structopt(long)]
+    no_cache: bool,
+
+    /// Ignore cached API responses, fresh ones are still stored
+    #[structopt(long, conflicts_with = "no-cache")]
+    refresh: bool,
+
+    /// For how many minutes cached train searches are used, the station list is kept for a week
+    #[structopt(long, value_name = "MINUTES", default_value = "30")]
+    cache_ttl: u64,
+
+    /// How many times a request failing with a server or network error is attempted before giving up
+    #[structopt(long, value_name = "N", default_value = "3")]
+    max_attempts: u32,
+
+    /// How results should be printed, json is a versioned document meant for scripts, csv and tsv
+    /// are meant for spreadsheets, markdown and html for sharing
+    #[structopt(long, default_value = "table")]
+    output: String,
+
+    /// Only show the first N journeys, after sorting
+    #[structopt(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Save trains of the shown journeys as events in this iCalendar file
+    #[structopt(
+        long,
+        value_name = "FILE",
+        parse(from_os_str),
+        conflicts_with = "one-way"
+    )]
+    ics: Option<PathBuf>,
+
+    /// Which of the shown journeys --ics should save, counted from 1 (e.g. 1,3), all of them by
+    /// default
+    #[structopt(long, value_name = "N", require_delimiter = true, requires = "ics")]
+    ics_journeys: Vec<usize>,
 
     /// How results should be sorted
-    #[structopt(long, possible_values = &SortBy::variants(), case_insensitive = true, default_value = "price")]
+    #[structopt(long, possible_values = &["price", "date", "days"], case_insensitive = true, default_value = "price")]
     sort_by: SortBy,
 
+    /// Which class of travel should be priced, any-cheapest picks the cheapest available one
+    #[structopt(long, possible_values = &["standard", "plus", "premier", "any-cheapest"], case_insensitive = true, default_value = "standard")]
+    class: ClassPreference,
+
     /// How many adults
     #[structopt(long, default_value = "1")]
     adults: i16,
 
+    /// How many children (aged 4-11)
+    #[structopt(long, default_value = "0")]
+    children: i16,
+
+    /// How many youths (aged 12-25)
+    #[structopt(long, default_value = "0")]
+    youths: i16,
+
+    /// How many seniors (aged 60+)
+    #[structopt(long, default_value = "0")]
+    seniors: i16,
+
     /// Start station
     #[structopt(default_value = "London")]
     from: String,
@@ -84,6 +218,14 @@ struct Opt {
     /// Finish station
     #[structopt(default_value = "Paris")]
     to: String,
+
+    /// Start station of the return journey, defaults to finish station
+    #[structopt(long, conflicts_with = "one-way")]
+    return_from: Option<String>,
+
+    /// Finish station of the return journey, defaults to start station
+    #[structopt(long, conflicts_with = "one-way")]
+    return_to: Option<String>,
 }
 
 #[tokio::main]
@@ -93,115 +235,332 @@ async fn main() {
 
     debug!("Parsed opts: {:#?}", opt);
 
-    if opt.from == opt.to {
-        clap::Error::value_validation_auto(
-            "Start and finish stations need to be different!".to_string(),
-        )
-        .exit();
+    // Recorded searches can be replayed long after their dates have passed
+    if opt.replay.is_none() {
+        for date in [opt.since, opt.until].iter() {
+            date::ensure_not_in_past(*date)
+                .unwrap_or_else(|err| clap::Error::value_validation_auto(err.to_string()).exit());
+        }
     }
 
-    let stations_map = get_stations_map(&opt.api_key).await.unwrap_or_else(|err| {
-        error!("{:?}", err);
-        std::process::exit(1);
-    });
+    let passengers = Passengers {
+        adults: opt.adults,
+        children: opt.children,
+        youths: opt.youths,
+        seniors: opt.seniors,
+    };
 
-    let mut stations: [i32; 2] = [0, 0];
-    for (i, station) in [opt.from, opt.to].iter().enumerate() {
-        stations[i] = parse_station(&station, &stations_map)
-            .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit());
-    }
+    let rates = opt.rates.as_ref().map(|path| {
+        ExchangeRates::from_file(path).unwrap_or_else(|err| {
+            clap::Error::value_validation_auto(format!(
+                "Failed to read exchange rates from {:?}: {}",
+                path, err
+            ))
+            .exit()
+        })
+    });
 
-    let travels = date::get_possible_travel_dates(opt.since, opt.until, opt.days, opt.weekday)
-        .unwrap_or_else(|err| clap::Error::value_validation_auto(err.to_string()).exit());
+    let formatters = Formatters::built_in();
+    let formatter = formatters.get(&opt.output).unwrap_or_else(|| {
+        clap::Error::value_validation_auto(format!(
+            "{} is an invalid output format, choose from: {}.",
+            opt.output,
+            formatters.names().join(", ")
+        ))
+        .exit()
+    });
 
-    if travels.is_empty() {
+    let markets = if opt.markets.is_empty() {
+        vec![Market {
+            market: opt.market.clone(),
+            language: opt.language.clone(),
+        }]
+    } else {
+        opt.markets.clone()
+    };
+    let market_currencies: Vec<Currency> = markets.iter().map(|market| market.currency()).collect();
+    if rates.is_none()
+        && market_currencies
+            .iter()
+            .any(|market_currency| *market_currency != market_currencies[0])
+    {
         clap::Error::value_validation_auto(
-            "There are not dates pair matching your criteria!".to_string(),
+            "--rates is needed to compare markets pricing in different currencies".to_string(),
         )
         .exit();
-    } else {
-        debug!("Possible travel dates: {:#?}", travels);
     }
+    // Markets are compared in the currency of the first one, unless asked otherwise
+    let currency = opt.display_currency.unwrap_or(market_currencies[0]);
+    // Checked before searching, so requests aren't wasted on prices which can't be converted
+    let market_rates: Vec<Decimal> = market_currencies
+        .iter()
+        .map(|market_currency| {
+            exchange_rate(rates.as_ref(), *market_currency, currency)
+                .unwrap_or_else(|err| exit_on_error(err))
+        })
+        .collect();
+    let converted = market_currencies
+        .iter()
+        .any(|market_currency| *market_currency != currency);
 
     let filter = Filter {
-        max_price: opt.max_price,
+        max_price: opt
+            .max_price
+            .map(|max_price| Money::new(max_price, currency)),
         out_departure_before: opt.out_departure_before,
         out_departure_after: opt.out_departure_after,
         in_departure_before: opt.in_departure_before,
         in_departure_after: opt.in_departure_after,
+        out_arrival_before: opt.out_arrive_before,
+        out_arrival_after: opt.out_arrive_after,
+        in_arrival_before: opt.in_arrive_before,
+        in_arrival_after: opt.in_arrive_after,
+        min_seats: Some(opt.min_seats.unwrap_or_else(|| passengers.count())),
+        direct_only: opt.direct_only,
     };
 
-    let journeys = match get_journeys(
-        &travels,
-        &opt.api_key,
-        stations[0],
-        stations[1],
-        opt.adults,
-        &filter,
-    )
-    .await
-    {
-        Ok(res) => res,
-        Err(err) => {
-            error!("{:?}", err);
-            std::process::exit(1);
+    let mut query = SearchQuery {
+        from: opt.from,
+        to: opt.to,
+        return_from: opt.return_from,
+        return_to: opt.return_to,
+        since: opt.since,
+        until: opt.until,
+        // --days is required unless --one-way is set
+        stay_lengths: opt.days.unwrap_or_default(),
+        weekdays: opt.weekday,
+        return_weekdays: opt.return_weekday,
+        passengers,
+        class: opt.class,
+        filter,
+    };
+    let recording = match (opt.record, opt.replay) {
+        (Some(dir), _) => Some(Recording::Record(dir)),
+        (_, Some(dir)) => Some(Recording::Replay(dir)),
+        (None, None) => None,
+    };
+    let api_key = opt.api_key.unwrap_or_default();
+    let config = ClientConfig {
+        base_url: opt.api_url,
+        market: opt.market,
+        language: opt.language,
+        limits: RequestLimits {
+            max_concurrent: opt.max_concurrent_requests,
+            per_second: Some(opt.requests_per_second).filter(|per_second| *per_second > 0.0),
+        },
+        retry: RetryPolicy {
+            max_attempts: opt.max_attempts,
+            ..RetryPolicy::default()
+        },
+        cache: match CacheConfig::in_user_cache_dir() {
+            Some(cache) if !opt.no_cache => Some(CacheConfig {
+                ttl: StdDuration::from_secs(opt.cache_ttl * 60),
+                refresh: opt.refresh,
+                ..cache
+            }),
+            _ => None,
+        },
+        recording,
+    };
+
+    // Converted prices are filtered once they are in the same currency as max price
+    let max_price = if converted {
+        query.filter.max_price.take()
+    } else {
+        None
+    };
+    let within_max_price = |price: Money| max_price.map_or(true, |max_price| price <= max_price);
+
+    if opt.one_way {
+        let client = Client::with_config(&api_key, &config);
+        let mut trains = client
+            .search_one_way(&query)
+            .await
+            .unwrap_or_else(|err| exit_on_error(err));
+        trains.results = trains
+            .results
+            .into_iter()
+            .map(|train| train.in_currency(currency, market_rates[0]))
+            .filter(|train| within_max_price(train.price))
+            .collect();
+        info!("Found {} trains matching criteria.", trains.results.len());
+        sort_trains(&mut trains.results, opt.sort_by);
+        if let Some(top) = opt.top {
+            trains.results.truncate(top);
+        }
+
+        let failures = failures_in(&markets[0], trains.failures);
+        // Filtered here, but still part of the query
+        if max_price.is_some() {
+            query.filter.max_price = max_price;
         }
+        let context = OutputContext {
+            query: &query,
+            currency,
+            markets: &markets,
+            rates: rates.as_ref().filter(|_| converted),
+            failures: &failures,
+        };
+
+        formatter
+            .write_trains(&mut io::stdout(), &trains.results, &context)
+            .unwrap_or_else(|err| exit_on_write_error(err));
+        exit_on_failures(&failures, false);
+        return;
+    }
+
+    let mut searches: Vec<(Market, Vec<TrainJourney>)> = Vec::new();
+    let mut failures = Vec::new();
+
+    // One market after another, so request limits hold for the whole comparison
+    for (market, rate) in markets.iter().zip(market_rates) {
+        let client = Client::with_config(
+            &api_key,
+            &ClientConfig {
+                market: market.market.clone(),
+                language: market.language.clone(),
+                ..config.clone()
+            },
+        );
+        let journeys = client
+            .search(&query)
+            .await
+            .unwrap_or_else(|err| exit_on_error(err));
+
+        info!(
+            "Found {} journeys matching criteria in {} market.",
+            journeys.results.len(),
+            market
+        );
+        failures.append(&mut failures_in(market, journeys.failures));
+        searches.push((
+            market.clone(),
+            journeys
+                .results
+                .into_iter()
+                .map(|journey| journey.in_currency(currency, rate))
+                .filter(|journey| within_max_price(journey.price))
+                .collect(),
+        ));
+    }
+
+    // Filtered here, but still part of the query
+    if max_price.is_some() {
+        query.filter.max_price = max_price;
+    }
+    let context = OutputContext {
+        query: &query,
+        currency,
+        markets: &markets,
+        rates: rates.as_ref().filter(|_| converted),
+        failures: &failures,
     };
 
-    if journeys.is_empty() {
-        println!("There was no journey matching supplied criteria :(")
+    if opt.markets.is_empty() {
+        let (_, mut journeys) = searches.remove(0);
+        sort_journeys(&mut journeys, opt.sort_by, |journey| journey);
+        if let Some(top) = opt.top {
+            journeys.truncate(top);
+        }
+
+        formatter
+            .write(&mut io::stdout(), &journeys, &context)
+            .unwrap_or_else(|err| exit_on_write_error(err));
+        if let Some(path) = &opt.ics {
+            save_calendar(path, journeys.iter().collect(), &opt.ics_journeys, &query);
+        }
     } else {
-        info!("Found {} journeys matching criteria.", journeys.len());
-        format_results(journeys, opt.sort_by).printstd();
+        // Already converted, so nothing is left for the comparison to convert
+        let mut journeys =
+            compare_markets(searches, currency, None).unwrap_or_else(|err| exit_on_error(err));
+        sort_journeys(&mut journeys, opt.sort_by, |found| &found.journey);
+        if let Some(top) = opt.top {
+            journeys.truncate(top);
+        }
+
+        formatter
+            .write_markets(&mut io::stdout(), &journeys, &context)
+            .unwrap_or_else(|err| exit_on_write_error(err));
+        if let Some(path) = &opt.ics {
+            let shown = journeys.iter().map(|found| &found.journey).collect();
+            save_calendar(path, shown, &opt.ics_journeys, &query);
+        }
     }
+    exit_on_failures(&failures, !opt.markets.is_empty());
 }
 
-fn format_results(mut journeys: Vec<TrainJourney>, sort_by: SortBy) -> Table {
-    // Always pre-sort on price
-    // journeys.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap());
+fn failures_in(market: &Market, failures: Vec<DateFailure>) -> Vec<(Market, DateFailure)> {
+    failures
+        .into_iter()
+        .map(|failure| (market.clone(), failure))
+        .collect()
+}
 
-    match sort_by {
-        SortBy::Price => journeys.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap()),
-        SortBy::Date => journeys.sort_by(|a, b| a.outbound.cmp(&b.outbound)),
+/// Journeys are picked the way they are shown, counted from 1
+fn save_calendar(path: &Path, shown: Vec<&TrainJourney>, picks: &[usize], query: &SearchQuery) {
+    let picked: Vec<TrainJourney> = if picks.is_empty() {
+        shown.into_iter().cloned().collect()
+    } else {
+        picks
+            .iter()
+            .map(
+                |pick| match pick.checked_sub(1).and_then(|index| shown.get(index)) {
+                    Some(journey) => (*journey).clone(),
+                    None => clap::Error::value_validation_auto(format!(
+                        "There is no journey {} to save, {} journeys were shown",
+                        pick,
+                        shown.len()
+                    ))
+                    .exit(),
+                },
+            )
+            .collect()
+    };
+
+    if picked.is_empty() {
+        eprintln!("There was no journey to save in {:?}", path);
+        return;
     }
 
-    let mut table = Table::new();
-    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-    table.set_titles(row!["Outbound (duration)", "Inbound (duration)", "Price"]);
-
-    for journey in journeys.iter() {
-        table.add_row(row![
-            format!(
-                "{} ({}h{}m)",
-                journey.outbound.format(RESULT_DATETIME_FORMAT),
-                journey.out_duration.num_hours(),
-                journey.out_duration.num_minutes() % 60
-            ),
-            format!(
-                "{} ({}h{}m)",
-                journey.inbound.format(RESULT_DATETIME_FORMAT),
-                journey.in_duration.num_hours(),
-                journey.out_duration.num_minutes() % 60
-            ),
-            journey.price
-        ]);
+    let save = || -> io::Result<()> {
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        write_ics(&mut writer, &picked, query)?;
+        writer.flush()
+    };
+    save().unwrap_or_else(|err| exit_on_write_error(err));
+    eprintln!("Saved {} journeys in {:?}", picked.len(), path);
+}
+
+fn exit_on_error(err: SearchError) -> ! {
+    match err {
+        SearchError::InvalidQuery(msg) => clap::Error::value_validation_auto(msg).exit(),
+        SearchError::QueryError(err) => {
+            error!("{}", err);
+            std::process::exit(1);
+        }
     }
-    table
 }
 
-fn parse_station(name: &str, station_map: &HashMap<String, i32>) -> Result<i32, String> {
-    match station_map.get(name) {
-        Some(res) => Ok(*res),
-        None => {
-            let mut keys: Vec<&str> = station_map.keys().map(|val| val.as_str()).collect();
-            keys.sort();
-            Err(format!(
-                "'{}' is invalid city name, choose from: {}.",
-                name,
-                keys.join(", ")
-            ))
+fn exit_on_write_error(err: io::Error) -> ! {
+    error!("Failed to write results: {}", err);
+    std::process::exit(1);
+}
+
+/// Results for other dates were already printed, so exit code differs from a failed search
+fn exit_on_failures(failures: &[(Market, DateFailure)], show_market: bool) {
+    if failures.is_empty() {
+        return;
+    }
+
+    eprintln!("Search failed for {} dates:", failures.len());
+    for (market, failure) in failures.iter() {
+        if show_market {
+            eprintln!("  {} {}", market, failure);
+        } else {
+            eprintln!("  {}", failure);
         }
     }
+    std::process::exit(PARTIAL_RESULTS_EXIT_CODE);
 }
 
 fn setup_logging(level: usize) {
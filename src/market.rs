@@ -0,0 +1,265 @@
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::client::SearchError;
+use crate::money::{Currency, Money};
+use crate::rates::{self, ExchangeRates};
+use crate::trains::{FareClass, TrainJourney};
+
+/// Market and language of the API, e.g. `uk-en` or `fr-fr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Market {
+    pub market: String,
+    pub language: String,
+}
+
+impl Market {
+    pub fn currency(&self) -> Currency {
+        Currency::from_market(&self.market)
+    }
+}
+
+impl FromStr for Market {
+    type Err = String;
+
+    fn from_str(locale: &str) -> Result<Market, String> {
+        match locale
+            .trim()
+            .to_lowercase()
+            .split('-')
+            .collect::<Vec<&str>>()[..]
+        {
+            [market, language] if !market.is_empty() && !language.is_empty() => Ok(Market {
+                market: market.to_string(),
+                language: language.to_string(),
+            }),
+            _ => Err(format!(
+                "{} is an invalid market, expected market and language (e.g. uk-en)!",
+                locale
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Market {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.market, self.language)
+    }
+}
+
+/// Journey offered in several markets, priced in a common currency
+#[derive(Debug, PartialEq)]
+pub struct MarketJourney {
+    /// Journey as found in the cheapest market, with its price converted
+    pub journey: TrainJourney,
+    pub market: Market,
+    /// Converted price in every market offering the journey, cheapest first
+    pub prices: Vec<(Market, Money)>,
+}
+
+/// Trains, times and classes identify a journey across markets
+type JourneyKey = (
+    String,
+    String,
+    FareClass,
+    FareClass,
+    NaiveDateTime,
+    NaiveDateTime,
+);
+
+fn journey_key(journey: &TrainJourney) -> JourneyKey {
+    (
+        journey.out_details.number.clone(),
+        journey.in_details.number.clone(),
+        journey.out_class,
+        journey.in_class,
+        journey.outbound,
+        journey.inbound,
+    )
+}
+
+/// Matches up journeys found in each market and picks the cheapest market for every one of them,
+/// `rates` are needed only for markets pricing in another currency
+pub fn compare_markets(
+    searches: Vec<(Market, Vec<TrainJourney>)>,
+    currency: Currency,
+    rates: Option<&ExchangeRates>,
+) -> Result<Vec<MarketJourney>, SearchError> {
+    let mut order = Vec::new();
+    let mut compared: HashMap<JourneyKey, MarketJourney> = HashMap::new();
+
+    for (market, journeys) in searches.into_iter() {
+        for journey in journeys.into_iter() {
+            let rate = rates::exchange_rate(rates, journey.price.currency, currency)?;
+            let journey = journey.in_currency(currency, rate);
+            let key = journey_key(&journey);
+
+            match compared.get_mut(&key) {
+                Some(found) => {
+                    found.prices.push((market.clone(), journey.price));
+                    if journey.price < found.journey.price {
+                        found.journey = journey;
+                        found.market = market.clone();
+                    }
+                }
+                None => {
+                    order.push(key.clone());
+                    compared.insert(
+                        key,
+                        MarketJourney {
+                            prices: vec![(market.clone(), journey.price)],
+                            journey,
+                            market: market.clone(),
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(order
+        .iter()
+        .filter_map(|key| compared.remove(key))
+        .map(|mut found| {
+            found.prices.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            found
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trains::{Fares, TrainDetails};
+    use chrono::{Duration, NaiveDate};
+    use maplit::hashmap;
+
+    fn market(locale: &str) -> Market {
+        locale.parse().unwrap()
+    }
+
+    fn money(amount: &str, currency: Currency) -> Money {
+        Money::new(amount.parse().unwrap(), currency)
+    }
+
+    fn create_journey(out_number: &str, price: Money) -> TrainJourney {
+        let outbound = NaiveDate::from_ymd(2020, 4, 5).and_hms(9, 0, 0);
+        let inbound = NaiveDate::from_ymd(2020, 4, 7).and_hms(18, 0, 0);
+        let details = |number: &str| TrainDetails {
+            number: number.to_string(),
+            direct: true,
+            customs_checks: false,
+            dob_required: false,
+        };
+
+        TrainJourney {
+            outbound,
+            inbound,
+            out_arrival: outbound + Duration::hours(2),
+            in_arrival: inbound + Duration::hours(2),
+            price,
+            fares: Fares {
+                adult: price,
+                ..Fares::zero(price.currency)
+            },
+            out_duration: Duration::hours(2),
+            in_duration: Duration::hours(2),
+            out_class: FareClass::Standard,
+            in_class: FareClass::Standard,
+            out_seats: None,
+            in_seats: None,
+            out_details: details(out_number),
+            in_details: details("9050"),
+        }
+    }
+
+    #[test]
+    fn test_parse_market() {
+        assert_eq!(
+            "FR-fr".parse::<Market>(),
+            Ok(Market {
+                market: "fr".to_string(),
+                language: "fr".to_string()
+            })
+        );
+        assert_eq!(market("uk-en").to_string(), "uk-en");
+        assert_eq!(market("uk-en").currency(), Currency::Gbp);
+        assert_eq!(market("be-nl").currency(), Currency::Eur);
+        assert!("uk".parse::<Market>().is_err());
+        assert!("uk-en-gb".parse::<Market>().is_err());
+    }
+
+    #[test]
+    fn test_compare_markets() {
+        let gbp = |amount| money(amount, Currency::Gbp);
+        let eur = |amount| money(amount, Currency::Eur);
+
+        let compared = compare_markets(
+            vec![
+                (
+                    market("uk-en"),
+                    vec![
+                        create_journey("9004", gbp("50")),
+                        create_journey("9008", gbp("80.5")),
+                    ],
+                ),
+                (
+                    market("fr-fr"),
+                    vec![
+                        create_journey("9004", eur("55")),
+                        create_journey("9012", eur("90")),
+                    ],
+                ),
+            ],
+            Currency::Eur,
+            Some(&ExchangeRates {
+                date: None,
+                base: Currency::Gbp,
+                rates: hashmap! {Currency::Eur => "1.15".parse().unwrap()},
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(compared.len(), 3);
+        assert_eq!(compared[0].market, market("fr-fr"));
+        assert_eq!(compared[0].journey.out_details.number, "9004");
+        assert_eq!(compared[0].journey.price, eur("55"));
+        assert_eq!(
+            compared[0].prices,
+            vec![(market("fr-fr"), eur("55")), (market("uk-en"), eur("57.5"))]
+        );
+        assert_eq!(compared[1].market, market("uk-en"));
+        // Rounded to cents
+        assert_eq!(compared[1].journey.price, eur("92.58"));
+        assert_eq!(compared[1].journey.fares.adult, eur("92.58"));
+        assert_eq!(compared[1].journey.fares.child, eur("0"));
+        assert_eq!(compared[2].market, market("fr-fr"));
+        assert_eq!(compared[2].prices, vec![(market("fr-fr"), eur("90"))]);
+    }
+
+    #[test]
+    fn test_compare_markets_without_rate() {
+        let searches = || {
+            vec![
+                (
+                    market("uk-en"),
+                    vec![create_journey("9004", money("50", Currency::Gbp))],
+                ),
+                (
+                    market("be-fr"),
+                    vec![create_journey("9004", money("55", Currency::Eur))],
+                ),
+            ]
+        };
+
+        match compare_markets(searches(), Currency::Gbp, None) {
+            Err(SearchError::InvalidQuery(msg)) => {
+                assert_eq!(msg, "No exchange rate from EUR to GBP!")
+            }
+            res => panic!("compare_markets returned: {:?}", res),
+        }
+        assert!(compare_markets(searches().split_off(1), Currency::Eur, None).is_ok());
+    }
+}
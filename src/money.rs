@@ -0,0 +1,151 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Currencies prices are given in, it depends on the market
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    Gbp,
+    Eur,
+}
+
+impl Currency {
+    /// Only the UK market prices in pounds, all the others use euros
+    pub fn from_market(market: &str) -> Currency {
+        match market.to_lowercase().as_str() {
+            "uk" => Currency::Gbp,
+            _ => Currency::Eur,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::Gbp => "£",
+            Currency::Eur => "€",
+        }
+    }
+}
+
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(currency: &str) -> Result<Currency, String> {
+        match currency.to_uppercase().as_str() {
+            "GBP" => Ok(Currency::Gbp),
+            "EUR" => Ok(Currency::Eur),
+            currency => Err(format!(
+                "{} is an unsupported currency, choose from: GBP, EUR.",
+                currency
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Currency::Gbp => write!(f, "GBP"),
+            Currency::Eur => write!(f, "EUR"),
+        }
+    }
+}
+
+/// Price kept as a decimal, so adding fares up doesn't accumulate rounding errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Money {
+    pub amount: Decimal,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: Decimal, currency: Currency) -> Money {
+        Money { amount, currency }
+    }
+
+    pub fn zero(currency: Currency) -> Money {
+        Money::new(Decimal::ZERO, currency)
+    }
+
+    /// Sum of both amounts, `None` if they are in different currencies
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        if self.currency == other.currency {
+            Some(Money::new(self.amount + other.amount, self.currency))
+        } else {
+            None
+        }
+    }
+
+    /// Converted amount is rounded to cents, nobody sells fractions of them
+    pub fn in_currency(self, currency: Currency, rate: Decimal) -> Money {
+        Money::new((self.amount * rate).round_dp(2), currency)
+    }
+}
+
+/// Amounts in different currencies can't be compared without an exchange rate
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Money) -> Option<Ordering> {
+        if self.currency == other.currency {
+            self.amount.partial_cmp(&other.amount)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{:.2}", self.currency.symbol(), self.amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(amount: &str, currency: Currency) -> Money {
+        Money::new(amount.parse().unwrap(), currency)
+    }
+
+    #[test]
+    fn test_money_checked_add() {
+        // 0.1 + 0.2 is not 0.3 with floats
+        assert_eq!(
+            money("0.1", Currency::Eur).checked_add(money("0.2", Currency::Eur)),
+            Some(money("0.3", Currency::Eur))
+        );
+        assert_eq!(
+            money("1", Currency::Eur).checked_add(money("1", Currency::Gbp)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_money_compare() {
+        assert!(money("9.99", Currency::Gbp) < money("10", Currency::Gbp));
+        assert_eq!(
+            money("9.99", Currency::Gbp).partial_cmp(&money("10", Currency::Eur)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_money_display() {
+        assert_eq!(money("78.5", Currency::Gbp).to_string(), "£78.50");
+        assert_eq!(money("157", Currency::Eur).to_string(), "€157.00");
+        assert_eq!(Currency::from_market("fr").to_string(), "EUR");
+        assert_eq!(Currency::from_market("UK").to_string(), "GBP");
+    }
+
+    #[test]
+    fn test_money_in_currency() {
+        assert_eq!(
+            money("80.5", Currency::Gbp).in_currency(Currency::Eur, "1.15".parse().unwrap()),
+            money("92.58", Currency::Eur)
+        );
+        assert_eq!("eur".parse::<Currency>(), Ok(Currency::Eur));
+        assert!("USD".parse::<Currency>().is_err());
+    }
+}
@@ -0,0 +1,873 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::client::SearchQuery;
+use crate::market::{Market, MarketJourney};
+use crate::money::{Currency, Money};
+use crate::rates::ExchangeRates;
+use crate::report::{HtmlFormatter, MarkdownFormatter};
+use crate::table::{DelimitedFormatter, TableFormatter};
+use crate::trains::{DateFailure, FareClass, Fares, Passengers, Train, TrainDetails, TrainJourney};
+
+/// Version of the JSON document, bumped whenever a field changes its meaning or disappears
+pub static JSON_VERSION: u32 = 1;
+
+/// What the results were found for
+#[derive(Debug)]
+pub struct OutputContext<'a> {
+    pub query: &'a SearchQuery,
+    /// Currency of every price in the results
+    pub currency: Currency,
+    /// Markets searched in, a single one unless prices were compared
+    pub markets: &'a [Market],
+    /// Set only if prices were converted from the currency of a market
+    pub rates: Option<&'a ExchangeRates>,
+    /// Dates which couldn't be searched, along with the market they failed in
+    pub failures: &'a [(Market, DateFailure)],
+}
+
+/// Writes journeys found for a query in some format, e.g. as a table or a JSON document
+pub trait Formatter {
+    fn write(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[TrainJourney],
+        context: &OutputContext,
+    ) -> io::Result<()>;
+
+    /// Journeys compared across markets, by default written without the markets
+    fn write_markets(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[MarketJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        let journeys: Vec<TrainJourney> =
+            journeys.iter().map(|found| found.journey.clone()).collect();
+        self.write(writer, &journeys, context)
+    }
+
+    /// Trains of a one-way search, by default refused as they aren't journeys
+    fn write_trains(
+        &self,
+        _writer: &mut dyn io::Write,
+        _trains: &[Train],
+        _context: &OutputContext,
+    ) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "one-way trains can't be written in this format",
+        ))
+    }
+}
+
+/// Formatters by their names, library users can register their own next to the built-in ones
+#[derive(Default)]
+pub struct Formatters {
+    formatters: Vec<(String, Box<dyn Formatter>)>,
+}
+
+impl Formatters {
+    pub fn new() -> Formatters {
+        Formatters::default()
+    }
+
+    /// Built-in formatters: table, json, csv, tsv, markdown and html
+    pub fn built_in() -> Formatters {
+        let mut formatters = Formatters::new();
+        formatters.register("table", TableFormatter);
+        formatters.register("json", JsonFormatter);
+        formatters.register("csv", DelimitedFormatter { delimiter: b',' });
+        formatters.register("tsv", DelimitedFormatter { delimiter: b'\t' });
+        formatters.register("markdown", MarkdownFormatter);
+        formatters.register("html", HtmlFormatter);
+        formatters
+    }
+
+    /// Replaces a formatter already registered under the same name
+    pub fn register<F: Formatter + 'static>(&mut self, name: &str, formatter: F) {
+        let name = name.to_lowercase();
+        self.formatters
+            .retain(|(registered, _)| *registered != name);
+        self.formatters.push((name, Box::new(formatter)));
+    }
+
+    /// Names are case insensitive
+    pub fn get(&self, name: &str) -> Option<&dyn Formatter> {
+        let name = name.to_lowercase();
+        self.formatters
+            .iter()
+            .find(|(registered, _)| *registered == name)
+            .map(|(_, formatter)| formatter.as_ref())
+    }
+
+    /// In the order formatters were registered in
+    pub fn names(&self) -> Vec<&str> {
+        self.formatters
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+impl fmt::Debug for Formatters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// How journeys are ordered before they are written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Price,
+    Date,
+    /// Length of the stay
+    Days,
+}
+
+impl FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(sort_by: &str) -> Result<SortBy, String> {
+        match sort_by.to_lowercase().as_str() {
+            "price" => Ok(SortBy::Price),
+            "date" => Ok(SortBy::Date),
+            "days" => Ok(SortBy::Days),
+            sort_by => Err(format!(
+                "{} is an invalid sort order, choose from: price, date, days.",
+                sort_by
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SortBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SortBy::Price => write!(f, "price"),
+            SortBy::Date => write!(f, "date"),
+            SortBy::Days => write!(f, "days"),
+        }
+    }
+}
+
+/// Sorts anything holding a journey, e.g. `TrainJourney` itself or `MarketJourney`
+///
+/// Prices have to be in a single currency, as after `TrainJourney::in_currency`.
+pub fn sort_journeys<T>(
+    journeys: &mut [T],
+    sort_by: SortBy,
+    journey: impl Fn(&T) -> &TrainJourney,
+) {
+    match sort_by {
+        SortBy::Price => {
+            debug_assert!(in_one_currency(
+                journeys.iter().map(|item| journey(item).price)
+            ));
+            journeys.sort_by(|a, b| journey(a).price.partial_cmp(&journey(b).price).unwrap())
+        }
+        SortBy::Date => journeys.sort_by_key(|item| journey(item).outbound),
+        SortBy::Days => journeys.sort_by_key(|item| journey(item).stay_length()),
+    }
+}
+
+/// Trains of a one-way search have no stay length, so days sort them by date
+///
+/// Prices have to be in a single currency, as after `Train::in_currency`.
+pub fn sort_trains(trains: &mut [Train], sort_by: SortBy) {
+    match sort_by {
+        SortBy::Price => {
+            debug_assert!(in_one_currency(trains.iter().map(|train| train.price)));
+            trains.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap())
+        }
+        SortBy::Date | SortBy::Days => trains.sort_by_key(|train| train.departure),
+    }
+}
+
+fn in_one_currency(mut prices: impl Iterator<Item = Money>) -> bool {
+    match prices.next() {
+        Some(first) => prices.all(|price| price.currency == first.currency),
+        None => true,
+    }
+}
+
+/// Versioned JSON document, see `write_json`
+#[derive(Debug, Default)]
+pub struct JsonFormatter;
+
+impl Formatter for JsonFormatter {
+    fn write(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[TrainJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        write_json(writer, journeys, context)
+    }
+
+    fn write_markets(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[MarketJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        write_market_json(writer, journeys, context)
+    }
+
+    fn write_trains(
+        &self,
+        writer: &mut dyn io::Write,
+        trains: &[Train],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        write_one_way_json(writer, trains, context)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    version: u32,
+    query: JsonQuery<'a>,
+    journeys: Vec<JsonJourney>,
+    failures: Vec<JsonFailure>,
+}
+
+#[derive(Serialize)]
+struct JsonQuery<'a> {
+    one_way: bool,
+    from: &'a str,
+    to: &'a str,
+    /// Not set for one-way searches, like everything else about the return
+    return_from: Option<&'a str>,
+    return_to: Option<&'a str>,
+    since: NaiveDate,
+    until: NaiveDate,
+    stay_lengths: Vec<i64>,
+    weekdays: &'a [Weekday],
+    return_weekdays: &'a [Weekday],
+    passengers: JsonPassengers,
+    class: String,
+    currency: Currency,
+    markets: Vec<String>,
+    exchange_rates_date: Option<NaiveDate>,
+    filter: JsonFilter,
+}
+
+#[derive(Serialize)]
+struct JsonPassengers {
+    adults: i16,
+    children: i16,
+    youths: i16,
+    seniors: i16,
+}
+
+#[derive(Serialize)]
+struct JsonFilter {
+    max_price: Option<String>,
+    out_departure_after: Option<NaiveTime>,
+    out_departure_before: Option<NaiveTime>,
+    in_departure_after: Option<NaiveTime>,
+    in_departure_before: Option<NaiveTime>,
+    out_arrival_after: Option<NaiveTime>,
+    out_arrival_before: Option<NaiveTime>,
+    in_arrival_after: Option<NaiveTime>,
+    in_arrival_before: Option<NaiveTime>,
+    min_seats: Option<i32>,
+    direct_only: bool,
+}
+
+#[derive(Serialize)]
+struct JsonJourney {
+    outbound: JsonLeg,
+    /// Not set for one-way searches, which find single trains
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inbound: Option<JsonLeg>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stay_length: Option<i64>,
+    /// Amounts are strings, so they are exact
+    price: String,
+    currency: Currency,
+    /// Fare of a single passenger of each type
+    fares: JsonFares,
+    /// Only set when markets were compared
+    #[serde(skip_serializing_if = "Option::is_none")]
+    market: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    market_prices: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize)]
+struct JsonLeg {
+    departure: NaiveDateTime,
+    arrival: NaiveDateTime,
+    duration_minutes: i64,
+    train: String,
+    direct: bool,
+    class: String,
+    seats: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct JsonFares {
+    adult: String,
+    child: String,
+    youth: String,
+    senior: String,
+}
+
+#[derive(Serialize)]
+struct JsonFailure {
+    market: String,
+    outbound: NaiveDate,
+    inbound: Option<NaiveDate>,
+    error: String,
+}
+
+/// Versioned JSON document with the query and every journey found, meant for scripts
+pub fn write_json<W: io::Write>(
+    writer: W,
+    journeys: &[TrainJourney],
+    context: &OutputContext,
+) -> io::Result<()> {
+    let journeys = journeys
+        .iter()
+        .map(|journey| json_journey(journey, None, &context.query.passengers))
+        .collect();
+    write_document(writer, journeys, context, false)
+}
+
+/// Same document as `write_json`, with the cheapest market and prices in each of them added to
+/// every journey
+pub fn write_market_json<W: io::Write>(
+    writer: W,
+    journeys: &[MarketJourney],
+    context: &OutputContext,
+) -> io::Result<()> {
+    let journeys = journeys
+        .iter()
+        .map(|found| json_journey(&found.journey, Some(found), &context.query.passengers))
+        .collect();
+    write_document(writer, journeys, context, false)
+}
+
+/// Same document as `write_json` for one-way searches, every journey is a single train with only
+/// its `outbound` leg
+pub fn write_one_way_json<W: io::Write>(
+    writer: W,
+    trains: &[Train],
+    context: &OutputContext,
+) -> io::Result<()> {
+    let journeys = trains
+        .iter()
+        .map(|train| JsonJourney {
+            outbound: json_leg(
+                train.departure,
+                train.arrival,
+                train.duration,
+                &train.details,
+                train.class,
+                train.seats,
+            ),
+            inbound: None,
+            stay_length: None,
+            price: json_amount(train.price),
+            currency: train.price.currency,
+            fares: json_fares(&context.query.passengers.per_person(&train.fares)),
+            market: None,
+            market_prices: None,
+        })
+        .collect();
+    write_document(writer, journeys, context, true)
+}
+
+fn write_document<W: io::Write>(
+    mut writer: W,
+    journeys: Vec<JsonJourney>,
+    context: &OutputContext,
+    one_way: bool,
+) -> io::Result<()> {
+    let document = JsonDocument {
+        version: JSON_VERSION,
+        query: json_query(context, one_way),
+        journeys,
+        failures: context
+            .failures
+            .iter()
+            .map(|(market, failure)| JsonFailure {
+                market: market.to_string(),
+                outbound: failure.outbound,
+                inbound: failure.inbound,
+                error: failure.error.to_string(),
+            })
+            .collect(),
+    };
+
+    serde_json::to_writer_pretty(&mut writer, &document)?;
+    writeln!(writer)
+}
+
+fn json_query<'a>(context: &OutputContext<'a>, one_way: bool) -> JsonQuery<'a> {
+    let query = context.query;
+    let filter = &query.filter;
+
+    JsonQuery {
+        one_way,
+        from: &query.from,
+        to: &query.to,
+        return_from: Some(query.return_from()).filter(|_| !one_way),
+        return_to: Some(query.return_to()).filter(|_| !one_way),
+        since: query.since,
+        until: query.until,
+        // Counted the same way as --days and `TrainJourney::stay_length`, both travel days included
+        stay_lengths: query
+            .stay_lengths
+            .0
+            .iter()
+            .map(|length| length.num_days() + 1)
+            .collect(),
+        weekdays: &query.weekdays,
+        return_weekdays: &query.return_weekdays,
+        passengers: JsonPassengers {
+            adults: query.passengers.adults,
+            children: query.passengers.children,
+            youths: query.passengers.youths,
+            seniors: query.passengers.seniors,
+        },
+        class: query.class.to_string(),
+        currency: context.currency,
+        markets: context
+            .markets
+            .iter()
+            .map(|market| market.to_string())
+            .collect(),
+        exchange_rates_date: context.rates.and_then(|rates| rates.date),
+        filter: JsonFilter {
+            max_price: filter.max_price.map(json_amount),
+            out_departure_after: filter.out_departure_after,
+            out_departure_before: filter.out_departure_before,
+            in_departure_after: filter.in_departure_after,
+            in_departure_before: filter.in_departure_before,
+            out_arrival_after: filter.out_arrival_after,
+            out_arrival_before: filter.out_arrival_before,
+            in_arrival_after: filter.in_arrival_after,
+            in_arrival_before: filter.in_arrival_before,
+            min_seats: filter.min_seats,
+            direct_only: filter.direct_only,
+        },
+    }
+}
+
+fn json_journey(
+    journey: &TrainJourney,
+    found: Option<&MarketJourney>,
+    passengers: &Passengers,
+) -> JsonJourney {
+    JsonJourney {
+        outbound: json_leg(
+            journey.outbound,
+            journey.out_arrival,
+            journey.out_duration,
+            &journey.out_details,
+            journey.out_class,
+            journey.out_seats,
+        ),
+        inbound: Some(json_leg(
+            journey.inbound,
+            journey.in_arrival,
+            journey.in_duration,
+            &journey.in_details,
+            journey.in_class,
+            journey.in_seats,
+        )),
+        stay_length: Some(journey.stay_length()),
+        price: json_amount(journey.price),
+        currency: journey.price.currency,
+        fares: json_fares(&passengers.per_person(&journey.fares)),
+        market: found.map(|found| found.market.to_string()),
+        market_prices: found.map(|found| {
+            found
+                .prices
+                .iter()
+                .map(|(market, price)| (market.to_string(), json_amount(*price)))
+                .collect()
+        }),
+    }
+}
+
+fn json_leg(
+    departure: NaiveDateTime,
+    arrival: NaiveDateTime,
+    duration: Duration,
+    details: &TrainDetails,
+    class: FareClass,
+    seats: Option<i32>,
+) -> JsonLeg {
+    JsonLeg {
+        departure,
+        arrival,
+        duration_minutes: duration.num_minutes(),
+        train: details.number.clone(),
+        direct: details.direct,
+        class: class.to_string().to_lowercase(),
+        seats,
+    }
+}
+
+fn json_fares(fares: &Fares) -> JsonFares {
+    JsonFares {
+        adult: json_amount(fares.adult),
+        child: json_amount(fares.child),
+        youth: json_amount(fares.youth),
+        senior: json_amount(fares.senior),
+    }
+}
+
+fn json_amount(money: Money) -> String {
+    format!("{:.2}", money.amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date::StayLengths;
+    use crate::trains::{ClassPreference, Filter, Passengers, QueryError};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn gbp(amount: &str) -> Money {
+        Money::new(amount.parse().unwrap(), Currency::Gbp)
+    }
+
+    fn create_query() -> SearchQuery {
+        SearchQuery {
+            from: "London".to_string(),
+            to: "Paris".to_string(),
+            return_from: None,
+            return_to: Some("Ashford".to_string()),
+            since: NaiveDate::from_ymd(2020, 4, 3),
+            until: NaiveDate::from_ymd(2020, 4, 10),
+            stay_lengths: StayLengths(vec![Duration::days(2)]),
+            weekdays: vec![Weekday::Fri],
+            return_weekdays: Vec::new(),
+            passengers: Passengers {
+                adults: 2,
+                children: 0,
+                youths: 0,
+                seniors: 0,
+            },
+            class: ClassPreference::AnyCheapest,
+            filter: Filter {
+                max_price: Some(gbp("200")),
+                out_departure_after: Some(NaiveTime::from_hms(18, 0, 0)),
+                direct_only: true,
+                ..Filter::default()
+            },
+        }
+    }
+
+    fn create_journey() -> TrainJourney {
+        let outbound = NaiveDate::from_ymd(2020, 4, 3).and_hms(18, 1, 0);
+        let inbound = NaiveDate::from_ymd(2020, 4, 5).and_hms(20, 33, 0);
+
+        TrainJourney {
+            outbound,
+            inbound,
+            out_arrival: outbound + Duration::minutes(196),
+            in_arrival: inbound + Duration::minutes(76),
+            price: gbp("157"),
+            fares: Fares {
+                adult: gbp("157"),
+                ..Fares::zero(Currency::Gbp)
+            },
+            out_duration: Duration::minutes(136),
+            in_duration: Duration::minutes(136),
+            out_class: FareClass::Standard,
+            in_class: FareClass::Plus,
+            out_seats: Some(50),
+            in_seats: None,
+            out_details: TrainDetails {
+                number: "9051".to_string(),
+                direct: true,
+                customs_checks: false,
+                dob_required: false,
+            },
+            in_details: TrainDetails {
+                number: "9058".to_string(),
+                direct: true,
+                customs_checks: false,
+                dob_required: false,
+            },
+        }
+    }
+
+    struct CountFormatter;
+
+    impl Formatter for CountFormatter {
+        fn write(
+            &self,
+            writer: &mut dyn io::Write,
+            journeys: &[TrainJourney],
+            _context: &OutputContext,
+        ) -> io::Result<()> {
+            writeln!(writer, "{} journeys", journeys.len())
+        }
+    }
+
+    #[test]
+    fn test_formatters() {
+        let mut formatters = Formatters::built_in();
+        assert_eq!(
+            formatters.names(),
+            vec!["table", "json", "csv", "tsv", "markdown", "html"]
+        );
+        assert!(formatters.get("JSON").is_some());
+        assert!(formatters.get("yaml").is_none());
+
+        formatters.register("Count", CountFormatter);
+        formatters.register("json", CountFormatter);
+        assert_eq!(formatters.names().len(), 7);
+
+        let query = create_query();
+        let markets: Vec<Market> = vec!["uk-en".parse().unwrap()];
+        let context = OutputContext {
+            query: &query,
+            currency: Currency::Gbp,
+            markets: &markets,
+            rates: None,
+            failures: &[],
+        };
+        let found = MarketJourney {
+            journey: create_journey(),
+            market: markets[0].clone(),
+            prices: vec![(markets[0].clone(), gbp("157"))],
+        };
+
+        let mut output = Vec::new();
+        let formatter = formatters.get("count").unwrap();
+        formatter
+            .write(&mut output, &[create_journey(), create_journey()], &context)
+            .unwrap();
+        // Markets are left out by default
+        formatter
+            .write_markets(&mut output, &[found], &context)
+            .unwrap();
+        formatters
+            .get("json")
+            .unwrap()
+            .write(&mut output, &[], &context)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "2 journeys\n1 journeys\n0 journeys\n"
+        );
+        assert!(formatter
+            .write_trains(&mut Vec::new(), &[], &context)
+            .is_err());
+    }
+
+    #[test]
+    fn test_sort_journeys() {
+        let cheap = TrainJourney {
+            price: gbp("100"),
+            inbound: NaiveDate::from_ymd(2020, 4, 4).and_hms(20, 0, 0),
+            ..create_journey()
+        };
+        let early = TrainJourney {
+            outbound: NaiveDate::from_ymd(2020, 4, 2).and_hms(18, 0, 0),
+            ..create_journey()
+        };
+        let mut journeys = vec![create_journey(), cheap.clone(), early.clone()];
+
+        sort_journeys(&mut journeys, "Price".parse().unwrap(), |journey| journey);
+        assert_eq!(journeys[0], cheap);
+        sort_journeys(&mut journeys, SortBy::Date, |journey| journey);
+        assert_eq!(journeys[0], early);
+        sort_journeys(&mut journeys, SortBy::Days, |journey| journey);
+        assert_eq!(journeys[0], cheap);
+        assert_eq!(journeys[2], early);
+
+        assert_eq!(SortBy::Days.to_string(), "days");
+        assert!("seats".parse::<SortBy>().is_err());
+    }
+
+    #[test]
+    fn test_write_json() {
+        let query = create_query();
+        let markets: Vec<Market> = vec!["uk-en".parse().unwrap()];
+        let failures = vec![(
+            markets[0].clone(),
+            DateFailure {
+                outbound: NaiveDate::from_ymd(2020, 4, 10),
+                inbound: Some(NaiveDate::from_ymd(2020, 4, 12)),
+                error: Arc::new(QueryError::ServerError("Got 503".to_string())),
+            },
+        )];
+        let context = OutputContext {
+            query: &query,
+            currency: Currency::Gbp,
+            markets: &markets,
+            rates: None,
+            failures: &failures,
+        };
+
+        let mut output = Vec::new();
+        write_json(&mut output, &[create_journey()], &context).unwrap();
+
+        let document: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(
+            document,
+            json!({
+                "version": 1,
+                "query": {
+                    "one_way": false,
+                    "from": "London",
+                    "to": "Paris",
+                    "return_from": "Paris",
+                    "return_to": "Ashford",
+                    "since": "2020-04-03",
+                    "until": "2020-04-10",
+                    "stay_lengths": [3],
+                    "weekdays": ["Fri"],
+                    "return_weekdays": [],
+                    "passengers": {"adults": 2, "children": 0, "youths": 0, "seniors": 0},
+                    "class": "any-cheapest",
+                    "currency": "GBP",
+                    "markets": ["uk-en"],
+                    "exchange_rates_date": null,
+                    "filter": {
+                        "max_price": "200.00",
+                        "out_departure_after": "18:00:00",
+                        "out_departure_before": null,
+                        "in_departure_after": null,
+                        "in_departure_before": null,
+                        "out_arrival_after": null,
+                        "out_arrival_before": null,
+                        "in_arrival_after": null,
+                        "in_arrival_before": null,
+                        "min_seats": null,
+                        "direct_only": true
+                    }
+                },
+                "journeys": [{
+                    "outbound": {
+                        "departure": "2020-04-03T18:01:00",
+                        "arrival": "2020-04-03T21:17:00",
+                        "duration_minutes": 136,
+                        "train": "9051",
+                        "direct": true,
+                        "class": "standard",
+                        "seats": 50
+                    },
+                    "inbound": {
+                        "departure": "2020-04-05T20:33:00",
+                        "arrival": "2020-04-05T21:49:00",
+                        "duration_minutes": 136,
+                        "train": "9058",
+                        "direct": true,
+                        "class": "plus",
+                        "seats": null
+                    },
+                    "stay_length": 3,
+                    "price": "157.00",
+                    "currency": "GBP",
+                    "fares": {"adult": "78.50", "child": "0.00", "youth": "0.00", "senior": "0.00"}
+                }],
+                "failures": [{
+                    "market": "uk-en",
+                    "outbound": "2020-04-10",
+                    "inbound": "2020-04-12",
+                    "error": "Got 503"
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_write_market_json() {
+        let query = create_query();
+        let markets: Vec<Market> = vec!["uk-en".parse().unwrap(), "fr-fr".parse().unwrap()];
+        let context = OutputContext {
+            query: &query,
+            currency: Currency::Gbp,
+            markets: &markets,
+            rates: None,
+            failures: &[],
+        };
+        let found = MarketJourney {
+            journey: create_journey(),
+            market: markets[1].clone(),
+            prices: vec![
+                (markets[1].clone(), gbp("157")),
+                (markets[0].clone(), gbp("160.5")),
+            ],
+        };
+
+        let mut output = Vec::new();
+        write_market_json(&mut output, &[found], &context).unwrap();
+
+        let document: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(document["query"]["markets"], json!(["uk-en", "fr-fr"]));
+        assert_eq!(document["journeys"][0]["market"], json!("fr-fr"));
+        assert_eq!(
+            document["journeys"][0]["market_prices"],
+            json!({"fr-fr": "157.00", "uk-en": "160.50"})
+        );
+    }
+
+    #[test]
+    fn test_write_one_way_json() {
+        let mut query = create_query();
+        query.stay_lengths = StayLengths(Vec::new());
+        let markets: Vec<Market> = vec!["uk-en".parse().unwrap()];
+        let context = OutputContext {
+            query: &query,
+            currency: Currency::Gbp,
+            markets: &markets,
+            rates: None,
+            failures: &[],
+        };
+        let journey = create_journey();
+        let train = Train {
+            departure: journey.outbound,
+            arrival: journey.out_arrival,
+            duration: journey.out_duration,
+            price: gbp("79"),
+            fares: Fares {
+                adult: gbp("79"),
+                ..Fares::zero(Currency::Gbp)
+            },
+            class: FareClass::Standard,
+            seats: None,
+            details: journey.out_details,
+        };
+
+        let mut output = Vec::new();
+        write_one_way_json(&mut output, &[train], &context).unwrap();
+
+        let document: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(document["query"]["one_way"], json!(true));
+        assert_eq!(document["query"]["return_from"], json!(null));
+        assert_eq!(document["query"]["stay_lengths"], json!([]));
+        assert_eq!(
+            document["journeys"],
+            json!([{
+                "outbound": {
+                    "departure": "2020-04-03T18:01:00",
+                    "arrival": "2020-04-03T21:17:00",
+                    "duration_minutes": 136,
+                    "train": "9051",
+                    "direct": true,
+                    "class": "standard",
+                    "seats": null
+                },
+                "price": "79.00",
+                "currency": "GBP",
+                "fares": {"adult": "39.50", "child": "0.00", "youth": "0.00", "senior": "0.00"}
+            }])
+        );
+    }
+}
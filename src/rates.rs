@@ -0,0 +1,211 @@
+use chrono::NaiveDate;
+use log::warn;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::client::SearchError;
+use crate::money::Currency;
+
+static RATES_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug)]
+pub enum RatesError {
+    IoError(io::Error),
+    ParseError(String),
+    InvalidRates(String),
+}
+
+impl fmt::Display for RatesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RatesError::IoError(err) => write!(f, "{}", err),
+            RatesError::ParseError(msg) | RatesError::InvalidRates(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// Exchange rates supplied by the user, the API itself only knows the currency of its market
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeRates {
+    /// When the rates were published, shown next to converted prices
+    pub date: Option<NaiveDate>,
+    pub base: Currency,
+    /// How much of each currency one unit of the base currency buys
+    pub rates: HashMap<Currency, Decimal>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RatesFile {
+    date: Option<String>,
+    base: String,
+    rates: HashMap<String, Decimal>,
+}
+
+impl ExchangeRates {
+    /// Reads TOML files (by their `.toml` extension) or JSON ones, e.g.
+    /// `{"date": "2020-04-01", "base": "EUR", "rates": {"GBP": 0.88}}`
+    pub fn from_file(path: &Path) -> Result<ExchangeRates, RatesError> {
+        let text = fs::read_to_string(path).map_err(RatesError::IoError)?;
+        let is_toml = path
+            .extension()
+            .map_or(false, |extension| extension.eq_ignore_ascii_case("toml"));
+
+        let file: RatesFile = if is_toml {
+            toml::from_str(&text).map_err(|err| RatesError::ParseError(err.to_string()))?
+        } else {
+            serde_json::from_str(&text).map_err(|err| RatesError::ParseError(err.to_string()))?
+        };
+
+        let date = match file.date {
+            Some(date) => Some(
+                NaiveDate::parse_from_str(&date, RATES_DATE_FORMAT)
+                    .map_err(|err| RatesError::ParseError(format!("{}: {}", date, err)))?,
+            ),
+            None => None,
+        };
+        let base = file.base.parse().map_err(RatesError::InvalidRates)?;
+
+        let mut rates = HashMap::new();
+        for (currency, rate) in file.rates.into_iter() {
+            if rate <= Decimal::ZERO {
+                return Err(RatesError::InvalidRates(format!(
+                    "Exchange rate for {} has to be positive!",
+                    currency
+                )));
+            }
+            // Rates files usually list far more currencies than Eurostar uses
+            match currency.parse() {
+                Ok(currency) => {
+                    rates.insert(currency, rate);
+                }
+                Err(_) => warn!("Ignoring exchange rate for {}", currency),
+            }
+        }
+
+        Ok(ExchangeRates { date, base, rates })
+    }
+
+    /// How much of `to` currency a unit of `from` buys
+    pub fn rate(&self, from: Currency, to: Currency) -> Option<Decimal> {
+        let per_base = |currency| {
+            if currency == self.base {
+                Some(Decimal::ONE)
+            } else {
+                self.rates.get(&currency).copied()
+            }
+        };
+
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+        Some(per_base(to)? / per_base(from)?)
+    }
+}
+
+/// Rate to convert prices with, no rates are needed to stay in the same currency
+pub fn exchange_rate(
+    rates: Option<&ExchangeRates>,
+    from: Currency,
+    to: Currency,
+) -> Result<Decimal, SearchError> {
+    if from == to {
+        return Ok(Decimal::ONE);
+    }
+    rates.and_then(|rates| rates.rate(from, to)).ok_or_else(|| {
+        SearchError::InvalidQuery(format!("No exchange rate from {} to {}!", from, to))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+    use std::process;
+
+    fn write_rates(name: &str, text: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("eurostarchecker-{}-{}", process::id(), name));
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_rates_from_json() {
+        let path = write_rates(
+            "rates.json",
+            r#"{"date": "2020-04-01", "base": "EUR", "rates": {"GBP": 0.88, "USD": 1.09}}"#,
+        );
+
+        assert_eq!(
+            ExchangeRates::from_file(&path).unwrap(),
+            ExchangeRates {
+                date: Some(NaiveDate::from_ymd(2020, 4, 1)),
+                base: Currency::Eur,
+                rates: hashmap! {Currency::Gbp => decimal("0.88")},
+            }
+        );
+    }
+
+    #[test]
+    fn test_rates_from_toml() {
+        let path = write_rates("rates.toml", "base = \"GBP\"\n\n[rates]\nEUR = 1.15\n");
+
+        let rates = ExchangeRates::from_file(&path).unwrap();
+        assert_eq!(rates.date, None);
+        assert_eq!(
+            rates.rate(Currency::Gbp, Currency::Eur),
+            Some(decimal("1.15"))
+        );
+    }
+
+    #[test]
+    fn test_rates_invalid() {
+        let path = write_rates("invalid.json", r#"{"base": "EUR", "rates": {"GBP": 0}}"#);
+        assert_eq!(
+            ExchangeRates::from_file(&path).unwrap_err().to_string(),
+            "Exchange rate for GBP has to be positive!"
+        );
+
+        let path = write_rates("invalid.toml", "base = \"EUR\"");
+        assert!(matches!(
+            ExchangeRates::from_file(&path),
+            Err(RatesError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_exchange_rate() {
+        let rates = ExchangeRates {
+            date: None,
+            base: Currency::Eur,
+            rates: hashmap! {Currency::Gbp => decimal("0.8")},
+        };
+
+        assert_eq!(
+            exchange_rate(Some(&rates), Currency::Eur, Currency::Gbp).unwrap(),
+            decimal("0.8")
+        );
+        assert_eq!(
+            exchange_rate(Some(&rates), Currency::Gbp, Currency::Eur).unwrap(),
+            decimal("1.25")
+        );
+        assert_eq!(
+            exchange_rate(None, Currency::Gbp, Currency::Gbp).unwrap(),
+            Decimal::ONE
+        );
+        match exchange_rate(None, Currency::Gbp, Currency::Eur) {
+            Err(SearchError::InvalidQuery(msg)) => {
+                assert_eq!(msg, "No exchange rate from GBP to EUR!")
+            }
+            res => panic!("exchange_rate returned: {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::cache::file_name;
+use crate::trains::QueryError;
+
+/// Raw API responses are stored in or served from a directory, so searches can be reproduced
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recording {
+    Record(PathBuf),
+    /// No requests are sent, every response has to be recorded beforehand
+    Replay(PathBuf),
+}
+
+/// Empty file stands for a response without any trains
+pub(crate) fn record(
+    dir: &Path,
+    location: &str,
+    query_params: &HashMap<&str, String>,
+    body: Option<&str>,
+) {
+    let path = dir.join(file_name(location, query_params));
+    match fs::create_dir_all(dir).and_then(|_| fs::write(&path, body.unwrap_or_default())) {
+        Ok(()) => debug!("Recorded response in {:?}", path),
+        Err(err) => warn!("Failed to record response in {:?}: {}", path, err),
+    }
+}
+
+pub(crate) fn replay(
+    dir: &Path,
+    location: &str,
+    query_params: &HashMap<&str, String>,
+) -> Result<Option<String>, QueryError> {
+    let path = dir.join(file_name(location, query_params));
+    debug!("Replaying response from {:?}", path);
+
+    match fs::read_to_string(&path) {
+        Ok(body) if body.is_empty() => Ok(None),
+        Ok(body) => Ok(Some(body)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Err(QueryError::InternalError(
+            format!("No recorded response for {} in {:?}", location, path),
+        )),
+        Err(err) => Err(QueryError::InternalError(format!(
+            "Failed to read recorded response {:?}: {}",
+            path, err
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn test_record_and_replay() {
+        let dir = std::env::temp_dir().join(format!("eurostarchecker-rec-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let trains = hashmap! {"outbound-date" => "2020-04-05".to_string()};
+        let no_trains = hashmap! {"outbound-date" => "2020-04-06".to_string()};
+
+        record(&dir, "train-search/uk-en/1/2", &trains, Some("{}"));
+        record(&dir, "train-search/uk-en/1/2", &no_trains, None);
+
+        assert_eq!(
+            replay(&dir, "train-search/uk-en/1/2", &trains).unwrap(),
+            Some("{}".to_string())
+        );
+        assert_eq!(
+            replay(&dir, "train-search/uk-en/1/2", &no_trains).unwrap(),
+            None
+        );
+        match replay(&dir, "train-search/uk-en/2/1", &trains) {
+            Err(QueryError::InternalError(err)) => {
+                assert!(err.starts_with("No recorded response for train-search/uk-en/2/1"))
+            }
+            res => panic!("replay returned {:?}, it should return InternalError!", res),
+        }
+    }
+}
@@ -0,0 +1,673 @@
+use chrono::{NaiveDate, Weekday};
+use prettytable::Row;
+use std::collections::HashMap;
+use std::io;
+
+use crate::market::MarketJourney;
+use crate::money::Money;
+use crate::output::{Formatter, OutputContext};
+use crate::rates::ExchangeRates;
+use crate::table::{
+    journey_row, journey_titles, market_row, market_titles, one_way_row, one_way_titles,
+    RESULT_ARRIVAL_FORMAT,
+};
+use crate::trains::{Train, TrainJourney};
+
+static HTML_STYLE: &str = "<style>
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }
+tr.cheapest { background: #e6f4e6; font-weight: bold; }
+</style>
+";
+
+/// Markdown with a summary of the query above the results, for sharing them in a chat or a wiki
+#[derive(Debug, Default)]
+pub struct MarkdownFormatter;
+
+/// HTML page with a summary of the query above the results
+#[derive(Debug, Default)]
+pub struct HtmlFormatter;
+
+impl Formatter for MarkdownFormatter {
+    fn write(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[TrainJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        write!(
+            writer,
+            "{}",
+            Report::for_journeys(journeys, context).to_markdown()
+        )
+    }
+
+    fn write_markets(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[MarketJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        write!(
+            writer,
+            "{}",
+            Report::for_markets(journeys, context).to_markdown()
+        )
+    }
+
+    fn write_trains(
+        &self,
+        writer: &mut dyn io::Write,
+        trains: &[Train],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        write!(writer, "{}", Report::one_way(trains, context).to_markdown())
+    }
+}
+
+impl Formatter for HtmlFormatter {
+    fn write(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[TrainJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        write!(
+            writer,
+            "{}",
+            Report::for_journeys(journeys, context).to_html()
+        )
+    }
+
+    fn write_markets(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[MarketJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        write!(
+            writer,
+            "{}",
+            Report::for_markets(journeys, context).to_html()
+        )
+    }
+
+    fn write_trains(
+        &self,
+        writer: &mut dyn io::Write,
+        trains: &[Train],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        write!(writer, "{}", Report::one_way(trains, context).to_html())
+    }
+}
+
+/// Results along with a summary of the query, for sharing them as markdown or HTML
+struct Report {
+    title: String,
+    summary: Vec<(&'static str, String)>,
+    titles: Row,
+    rows: Vec<Row>,
+    /// Which rows are the cheapest for their dates
+    cheapest: Vec<bool>,
+    /// What a row is, journey or train
+    kind: &'static str,
+    /// What the cheapest rows are the cheapest of
+    cheapest_of: &'static str,
+}
+
+impl Report {
+    fn for_journeys(journeys: &[TrainJourney], context: &OutputContext) -> Report {
+        let rows = journeys
+            .iter()
+            .map(|journey| journey_row(journey, &context.query.passengers))
+            .collect();
+        Report::new(context, journey_titles(), rows, journeys.iter().collect())
+    }
+
+    fn for_markets(journeys: &[MarketJourney], context: &OutputContext) -> Report {
+        let rows = journeys
+            .iter()
+            .map(|found| {
+                market_row(
+                    journey_row(&found.journey, &context.query.passengers),
+                    found,
+                )
+            })
+            .collect();
+        Report::new(
+            context,
+            market_titles(journey_titles()),
+            rows,
+            journeys.iter().map(|found| &found.journey).collect(),
+        )
+    }
+
+    fn new(
+        context: &OutputContext,
+        titles: Row,
+        rows: Vec<Row>,
+        journeys: Vec<&TrainJourney>,
+    ) -> Report {
+        let query = context.query;
+        let dates = journeys
+            .iter()
+            .map(|journey| {
+                (
+                    (journey.outbound.date(), Some(journey.inbound.date())),
+                    journey.price,
+                )
+            })
+            .collect();
+        Report {
+            title: format!("Eurostar journeys {} - {}", query.from, query.to),
+            summary: summarise_query(context, false),
+            titles,
+            rows,
+            cheapest: cheapest_per_dates(dates),
+            kind: "journey",
+            cheapest_of: "journey for each pair of dates",
+        }
+    }
+
+    fn one_way(trains: &[Train], context: &OutputContext) -> Report {
+        let query = context.query;
+        let dates = trains
+            .iter()
+            .map(|train| ((train.departure.date(), None), train.price))
+            .collect();
+        Report {
+            title: format!("Eurostar trains {} - {}", query.from, query.to),
+            summary: summarise_query(context, true),
+            titles: one_way_titles(),
+            rows: trains
+                .iter()
+                .map(|train| one_way_row(train, &query.passengers))
+                .collect(),
+            cheapest: cheapest_per_dates(dates),
+            kind: "train",
+            cheapest_of: "train of each date",
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut res = format!("## {}\n\n", self.title);
+        for (name, value) in self.summary.iter() {
+            res.push_str(&format!("- **{}:** {}\n", name, escape_markdown(value)));
+        }
+        res.push('\n');
+
+        if self.rows.is_empty() {
+            res.push_str(&format!(
+                "There was no {} matching supplied criteria :(\n",
+                self.kind
+            ));
+            return res;
+        }
+
+        let cells = |row: &Row, strong: bool| -> String {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|cell| match escape_markdown(&cell.get_content()) {
+                    // `****` would be shown as is
+                    content if strong && !content.is_empty() => format!("**{}**", content),
+                    content => content,
+                })
+                .collect();
+            format!("| {} |\n", cells.join(" | "))
+        };
+
+        res.push_str(&cells(&self.titles, false));
+        res.push_str(&format!("|{}\n", " --- |".repeat(self.titles.len())));
+        for (row, cheapest) in self.rows.iter().zip(self.cheapest.iter()) {
+            res.push_str(&cells(row, *cheapest));
+        }
+        res.push_str(&format!(
+            "\nThe cheapest {} is in bold.\n",
+            self.cheapest_of
+        ));
+        res
+    }
+
+    fn to_html(&self) -> String {
+        let mut res = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n{}</head>\n<body>\n<h1>{}</h1>\n<ul>\n",
+            escape_html(&self.title),
+            HTML_STYLE,
+            escape_html(&self.title)
+        );
+        for (name, value) in self.summary.iter() {
+            res.push_str(&format!(
+                "<li><strong>{}:</strong> {}</li>\n",
+                name,
+                escape_html(value)
+            ));
+        }
+        res.push_str("</ul>\n");
+
+        if self.rows.is_empty() {
+            res.push_str(&format!(
+                "<p>There was no {} matching supplied criteria :(</p>\n",
+                self.kind
+            ));
+        } else {
+            let cells = |row: &Row, tag: &str| -> String {
+                row.iter()
+                    .map(|cell| format!("<{0}>{1}</{0}>", tag, escape_html(&cell.get_content())))
+                    .collect()
+            };
+
+            res.push_str("<table>\n<thead>\n");
+            res.push_str(&format!("<tr>{}</tr>\n", cells(&self.titles, "th")));
+            res.push_str("</thead>\n<tbody>\n");
+            for (row, cheapest) in self.rows.iter().zip(self.cheapest.iter()) {
+                let class = if *cheapest { " class=\"cheapest\"" } else { "" };
+                res.push_str(&format!("<tr{}>{}</tr>\n", class, cells(row, "td")));
+            }
+            res.push_str("</tbody>\n</table>\n");
+            res.push_str(&format!(
+                "<p>The cheapest {} is highlighted.</p>\n",
+                self.cheapest_of
+            ));
+        }
+        res.push_str("</body>\n</html>\n");
+        res
+    }
+}
+
+/// Route, dates, stay lengths and filters, as they were searched for
+fn summarise_query(context: &OutputContext, one_way: bool) -> Vec<(&'static str, String)> {
+    let query = context.query;
+    let filter = &query.filter;
+
+    let weekdays = |weekdays: &[Weekday]| {
+        let names: Vec<String> = weekdays.iter().map(|day| day.to_string()).collect();
+        names.join(", ")
+    };
+    let mut dates = format!("{} to {}", query.since, query.until);
+    if !query.weekdays.is_empty() {
+        dates.push_str(&format!(", leaving on {}", weekdays(&query.weekdays)));
+    }
+    if !query.return_weekdays.is_empty() {
+        dates.push_str(&format!(
+            ", returning on {}",
+            weekdays(&query.return_weekdays)
+        ));
+    }
+
+    // Counted the same way as --days, both travel days included
+    let stay_lengths: Vec<String> = query
+        .stay_lengths
+        .0
+        .iter()
+        .map(|length| (length.num_days() + 1).to_string())
+        .collect();
+
+    let mut passengers = Vec::new();
+    for (count, one, many) in [
+        (query.passengers.adults, "adult", "adults"),
+        (query.passengers.children, "child", "children"),
+        (query.passengers.youths, "youth", "youths"),
+        (query.passengers.seniors, "senior", "seniors"),
+    ]
+    .iter()
+    {
+        match count {
+            0 => {}
+            1 => passengers.push(format!("1 {}", one)),
+            count => passengers.push(format!("{} {}", count, many)),
+        }
+    }
+
+    let mut filters = Vec::new();
+    if let Some(max_price) = filter.max_price {
+        filters.push(format!("max price {}", max_price));
+    }
+    for (name, time) in [
+        ("outbound departure after", filter.out_departure_after),
+        ("outbound departure before", filter.out_departure_before),
+        ("inbound departure after", filter.in_departure_after),
+        ("inbound departure before", filter.in_departure_before),
+        ("outbound arrival after", filter.out_arrival_after),
+        ("outbound arrival before", filter.out_arrival_before),
+        ("inbound arrival after", filter.in_arrival_after),
+        ("inbound arrival before", filter.in_arrival_before),
+    ]
+    .iter()
+    {
+        if let Some(time) = time {
+            filters.push(format!("{} {}", name, time.format(RESULT_ARRIVAL_FORMAT)));
+        }
+    }
+    if let Some(min_seats) = filter.min_seats {
+        match min_seats {
+            1 => filters.push("at least 1 seat left".to_string()),
+            min_seats => filters.push(format!("at least {} seats left", min_seats)),
+        }
+    }
+    if filter.direct_only {
+        filters.push("direct trains only".to_string());
+    }
+
+    let mut summary = if one_way {
+        vec![
+            ("Route", format!("{} - {}, one way", query.from, query.to)),
+            ("Dates", dates),
+        ]
+    } else {
+        vec![
+            (
+                "Route",
+                format!(
+                    "{} - {}, back {} - {}",
+                    query.from,
+                    query.to,
+                    query.return_from(),
+                    query.return_to()
+                ),
+            ),
+            ("Dates", dates),
+            ("Days", stay_lengths.join(", ")),
+        ]
+    };
+    summary.push(("Passengers", passengers.join(", ")));
+    summary.push(("Class", query.class.to_string()));
+    summary.push(("Filters", filters.join(", ")));
+    if context.markets.len() > 1 {
+        let markets: Vec<String> = context
+            .markets
+            .iter()
+            .map(|market| market.to_string())
+            .collect();
+        summary.push(("Markets", markets.join(", ")));
+    }
+    match context.rates {
+        Some(ExchangeRates {
+            date: Some(date), ..
+        }) => summary.push((
+            "Prices",
+            format!(
+                "in {}, converted with exchange rates from {}",
+                context.currency, date
+            ),
+        )),
+        Some(_) => summary.push((
+            "Prices",
+            format!(
+                "in {}, converted with supplied exchange rates",
+                context.currency
+            ),
+        )),
+        None => summary.push(("Prices", format!("in {}", context.currency))),
+    }
+    summary
+}
+
+/// Marks the first of the cheapest prices for every outbound date, paired with the inbound one for
+/// return journeys
+fn cheapest_per_dates(prices: Vec<((NaiveDate, Option<NaiveDate>), Money)>) -> Vec<bool> {
+    let mut cheapest: HashMap<(NaiveDate, Option<NaiveDate>), usize> = HashMap::new();
+    for (index, (dates, price)) in prices.iter().enumerate() {
+        match cheapest.get(dates) {
+            Some(found) if prices[*found].1 <= *price => {}
+            _ => {
+                cheapest.insert(*dates, index);
+            }
+        }
+    }
+
+    let mut res = vec![false; prices.len()];
+    for index in cheapest.values() {
+        res[*index] = true;
+    }
+    res
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|").replace('*', "\\*")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::SearchQuery;
+    use crate::date::StayLengths;
+    use crate::market::Market;
+    use crate::money::Currency;
+    use crate::trains::{ClassPreference, FareClass, Fares, Filter, Passengers, TrainDetails};
+    use chrono::{Duration, NaiveTime};
+
+    fn gbp(amount: &str) -> Money {
+        Money::new(amount.parse().unwrap(), Currency::Gbp)
+    }
+
+    fn create_query() -> SearchQuery {
+        SearchQuery {
+            from: "London".to_string(),
+            to: "Paris".to_string(),
+            return_from: None,
+            return_to: Some("Ashford".to_string()),
+            since: NaiveDate::from_ymd(2020, 4, 3),
+            until: NaiveDate::from_ymd(2020, 4, 10),
+            stay_lengths: StayLengths(vec![Duration::days(2)]),
+            weekdays: vec![Weekday::Fri],
+            return_weekdays: Vec::new(),
+            passengers: Passengers {
+                adults: 2,
+                children: 0,
+                youths: 0,
+                seniors: 0,
+            },
+            class: ClassPreference::AnyCheapest,
+            filter: Filter {
+                max_price: Some(gbp("200")),
+                out_departure_after: Some(NaiveTime::from_hms(18, 0, 0)),
+                direct_only: true,
+                ..Filter::default()
+            },
+        }
+    }
+
+    fn create_journey() -> TrainJourney {
+        let outbound = NaiveDate::from_ymd(2020, 4, 3).and_hms(18, 1, 0);
+        let inbound = NaiveDate::from_ymd(2020, 4, 5).and_hms(20, 33, 0);
+
+        TrainJourney {
+            outbound,
+            inbound,
+            out_arrival: outbound + Duration::minutes(196),
+            in_arrival: inbound + Duration::minutes(76),
+            price: gbp("157"),
+            fares: Fares {
+                adult: gbp("157"),
+                ..Fares::zero(Currency::Gbp)
+            },
+            out_duration: Duration::minutes(136),
+            in_duration: Duration::minutes(136),
+            out_class: FareClass::Standard,
+            in_class: FareClass::Plus,
+            out_seats: Some(50),
+            in_seats: None,
+            out_details: TrainDetails {
+                number: "9051".to_string(),
+                direct: true,
+                customs_checks: false,
+                dob_required: false,
+            },
+            in_details: TrainDetails {
+                number: "9058".to_string(),
+                direct: true,
+                customs_checks: false,
+                dob_required: false,
+            },
+        }
+    }
+
+    fn create_journeys() -> Vec<TrainJourney> {
+        let later = create_journey().outbound + Duration::hours(1);
+        vec![
+            create_journey(),
+            TrainJourney {
+                outbound: later,
+                price: gbp("120"),
+                ..create_journey()
+            },
+            TrainJourney {
+                outbound: later + Duration::days(1),
+                price: gbp("200"),
+                ..create_journey()
+            },
+        ]
+    }
+
+    fn create_context<'a>(query: &'a SearchQuery, markets: &'a [Market]) -> OutputContext<'a> {
+        OutputContext {
+            query,
+            currency: Currency::Gbp,
+            markets,
+            rates: None,
+            failures: &[],
+        }
+    }
+
+    #[test]
+    fn test_cheapest_per_dates() {
+        let day = NaiveDate::from_ymd(2020, 4, 3);
+        let next_day = day.succ();
+        assert_eq!(
+            cheapest_per_dates(vec![
+                ((day, Some(next_day)), gbp("157")),
+                ((day, Some(next_day)), gbp("120")),
+                ((next_day, Some(next_day)), gbp("200")),
+                ((day, None), gbp("200")),
+                ((day, None), gbp("200")),
+            ]),
+            vec![false, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn test_markdown_formatter() {
+        let query = create_query();
+        let markets: Vec<Market> = vec!["uk-en".parse().unwrap()];
+
+        let mut output = Vec::new();
+        MarkdownFormatter
+            .write(
+                &mut output,
+                &create_journeys(),
+                &create_context(&query, &markets),
+            )
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "## Eurostar journeys London - Paris");
+        assert_eq!(
+            lines[2..10],
+            [
+                "- **Route:** London - Paris, back Paris - Ashford",
+                "- **Dates:** 2020-04-03 to 2020-04-10, leaving on Fri",
+                "- **Days:** 3",
+                "- **Passengers:** 2 adults",
+                "- **Class:** any-cheapest",
+                "- **Filters:** max price £200.00, outbound departure after 18:00, direct trains only",
+                "- **Prices:** in GBP",
+                "",
+            ]
+        );
+        assert_eq!(
+            lines[11],
+            "| --- | --- | --- | --- | --- | --- | --- | --- |"
+        );
+        assert!(lines[12].starts_with("| 2020-04-03 18:01 - 21:17 (2h16m) |"));
+        assert!(lines[13].starts_with("| **2020-04-03 19:01 - 21:17 (2h16m)** |"));
+        assert!(lines[14].starts_with("| **2020-04-04 19:01"));
+    }
+
+    #[test]
+    fn test_markdown_formatter_markets() {
+        let query = create_query();
+        let markets: Vec<Market> = vec!["uk-en".parse().unwrap(), "fr-fr".parse().unwrap()];
+        let found = MarketJourney {
+            journey: create_journey(),
+            market: markets[0].clone(),
+            prices: vec![(markets[0].clone(), gbp("157"))],
+        };
+
+        let mut output = Vec::new();
+        MarkdownFormatter
+            .write_markets(&mut output, &[found], &create_context(&query, &markets))
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("- **Markets:** uk-en, fr-fr\n"));
+        assert!(output.contains("| **£157.00** | **adult £78.50** | **uk-en** |  |\n"));
+        assert!(!output.contains("****"));
+    }
+
+    #[test]
+    fn test_markdown_formatter_trains() {
+        let query = create_query();
+        let markets: Vec<Market> = vec!["uk-en".parse().unwrap()];
+        let journey = create_journey();
+        let train = Train {
+            departure: journey.outbound,
+            arrival: journey.out_arrival,
+            duration: journey.out_duration,
+            price: gbp("79"),
+            fares: Fares {
+                adult: gbp("79"),
+                ..Fares::zero(Currency::Gbp)
+            },
+            class: FareClass::Standard,
+            seats: None,
+            details: journey.out_details,
+        };
+
+        let mut output = Vec::new();
+        MarkdownFormatter
+            .write_trains(&mut output, &[train], &create_context(&query, &markets))
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "## Eurostar trains London - Paris");
+        assert_eq!(lines[2], "- **Route:** London - Paris, one way");
+        assert!(!output.contains("- **Days:**"));
+        assert!(output.contains(
+            "| **2020-04-03 18:01 - 21:17 (2h16m)** | **9051** | **Standard** | **?** | **£79.00** | **adult £39.50** |\n"
+        ));
+        assert!(output.ends_with("The cheapest train of each date is in bold.\n"));
+    }
+
+    #[test]
+    fn test_html_formatter() {
+        let query = SearchQuery {
+            from: "<London>".to_string(),
+            ..create_query()
+        };
+        let markets: Vec<Market> = vec!["uk-en".parse().unwrap()];
+
+        let mut output = Vec::new();
+        HtmlFormatter
+            .write(
+                &mut output,
+                &create_journeys(),
+                &create_context(&query, &markets),
+            )
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("<h1>Eurostar journeys &lt;London&gt; - Paris</h1>"));
+        assert_eq!(output.matches("<tr class=\"cheapest\">").count(), 2);
+        assert!(output.ends_with("</html>\n"));
+    }
+}
@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::cmp;
+use std::time::Duration;
+
+/// How failed requests (server errors, throttling and transport errors) are retried
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of attempts including the first one, 1 disables retrying
+    pub max_attempts: u32,
+    /// Delay before the first retry, it doubles with every following one
+    pub initial_backoff: Duration,
+    /// Longest delay before a retry, also when the server asks for a longer one with `Retry-After`
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff after given failed attempt, half of it is randomised so that requests
+    /// which failed together don't come back at the same time
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .checked_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .unwrap_or(self.max_backoff);
+        let backoff = cmp::min(exponential, self.max_backoff);
+        backoff / 2 + backoff.mul_f64(rand::thread_rng().gen_range(0.0, 0.5))
+    }
+
+    /// Delay requested by the server if any, otherwise our own backoff
+    pub(crate) fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        match retry_after {
+            Some(retry_after) => cmp::min(retry_after, self.max_backoff),
+            None => self.backoff(attempt),
+        }
+    }
+}
+
+/// Delay requested by the server, given either in seconds or as a HTTP date
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+        };
+
+        for (attempt, expected) in [(1, 1), (2, 2), (3, 4), (4, 5), (40, 5)].iter() {
+            let backoff = policy.backoff(*attempt);
+            let expected = Duration::from_secs(*expected);
+            assert!(
+                backoff >= expected / 2 && backoff <= expected,
+                "backoff for attempt {} was {:?}",
+                attempt,
+                backoff
+            );
+        }
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(2))),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(24 * 60 * 60))),
+            policy.max_backoff
+        );
+        assert!(policy.delay(1, None) <= policy.initial_backoff);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        // Date in the past, our own backoff is used instead
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+}
@@ -0,0 +1,681 @@
+use chrono::{Duration, NaiveDateTime};
+use prettytable::{format, row, Cell, Row, Table};
+use std::io;
+
+use crate::market::MarketJourney;
+use crate::money::Money;
+use crate::output::{Formatter, OutputContext};
+use crate::trains::{FareClass, Fares, Passengers, Train, TrainDetails, TrainJourney};
+
+static RESULT_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+pub(crate) static RESULT_ARRIVAL_FORMAT: &str = "%H:%M";
+static DELIMITED_DATE_FORMAT: &str = "%Y-%m-%d";
+static DELIMITED_TIME_FORMAT: &str = "%H:%M";
+static LOW_AVAILABILITY_SEATS: i32 = 10;
+
+/// Table meant for a terminal, with a note below it if prices were converted
+#[derive(Debug, Default)]
+pub struct TableFormatter;
+
+impl Formatter for TableFormatter {
+    fn write(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[TrainJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        let rows = journeys
+            .iter()
+            .map(|journey| journey_row(journey, &context.query.passengers))
+            .collect();
+        write_table(writer, journey_titles(), rows, "journey", context)
+    }
+
+    fn write_markets(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[MarketJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        let rows = journeys
+            .iter()
+            .map(|found| {
+                market_row(
+                    journey_row(&found.journey, &context.query.passengers),
+                    found,
+                )
+            })
+            .collect();
+        write_table(
+            writer,
+            market_titles(journey_titles()),
+            rows,
+            "journey",
+            context,
+        )
+    }
+
+    fn write_trains(
+        &self,
+        writer: &mut dyn io::Write,
+        trains: &[Train],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        let rows = trains
+            .iter()
+            .map(|train| one_way_row(train, &context.query.passengers))
+            .collect();
+        write_table(writer, one_way_titles(), rows, "train", context)
+    }
+}
+
+/// Same results as the table, but with every value in a column of its own and without
+/// decorations, e.g. legs split into dates, times and duration and prices as plain amounts, so
+/// spreadsheets can work with them
+#[derive(Debug)]
+pub struct DelimitedFormatter {
+    /// E.g. `b','` for CSV or `b'\t'` for TSV
+    pub delimiter: u8,
+}
+
+impl Formatter for DelimitedFormatter {
+    fn write(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[TrainJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        let passengers = &context.query.passengers;
+        let mut table = Table::new();
+        table.set_titles(delimited_titles(&["Outbound", "Inbound"], passengers));
+
+        for journey in journeys.iter() {
+            table.add_row(delimited_row(journey, passengers));
+        }
+        self.write_delimited(writer, &table)
+    }
+
+    fn write_markets(
+        &self,
+        writer: &mut dyn io::Write,
+        journeys: &[MarketJourney],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        let passengers = &context.query.passengers;
+        let mut titles = delimited_titles(&["Outbound", "Inbound"], passengers);
+        titles.add_cell(Cell::new("Market"));
+        for market in context.markets.iter() {
+            titles.add_cell(Cell::new(&format!("Price {}", market)));
+        }
+
+        let mut table = Table::new();
+        table.set_titles(titles);
+
+        for found in journeys.iter() {
+            let mut row = delimited_row(&found.journey, passengers);
+            row.add_cell(Cell::new(&found.market.to_string()));
+            // Empty where the journey isn't offered
+            for market in context.markets.iter() {
+                let price = found
+                    .prices
+                    .iter()
+                    .find(|(offered_in, _)| offered_in == market)
+                    .map(|(_, price)| format!("{:.2}", price.amount));
+                row.add_cell(Cell::new(&price.unwrap_or_default()));
+            }
+            table.add_row(row);
+        }
+        self.write_delimited(writer, &table)
+    }
+
+    fn write_trains(
+        &self,
+        writer: &mut dyn io::Write,
+        trains: &[Train],
+        context: &OutputContext,
+    ) -> io::Result<()> {
+        let passengers = &context.query.passengers;
+        let mut table = Table::new();
+        table.set_titles(delimited_titles(&["Outbound"], passengers));
+
+        for train in trains.iter() {
+            let mut row = Row::empty();
+            delimited_leg(
+                &mut row,
+                (train.departure, train.arrival, train.duration),
+                &train.details,
+                train.class,
+                train.seats,
+            );
+            delimited_price(&mut row, train.price, &train.fares, passengers);
+            table.add_row(row);
+        }
+        self.write_delimited(writer, &table)
+    }
+}
+
+impl DelimitedFormatter {
+    fn write_delimited(&self, writer: &mut dyn io::Write, table: &Table) -> io::Result<()> {
+        let writer = csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(writer);
+        table.to_csv_writer(writer)?.flush()
+    }
+}
+
+/// `kind` of the rows names them when there are none
+fn write_table(
+    writer: &mut dyn io::Write,
+    titles: Row,
+    rows: Vec<Row>,
+    kind: &str,
+    context: &OutputContext,
+) -> io::Result<()> {
+    if rows.is_empty() {
+        return writeln!(
+            writer,
+            "There was no {} matching supplied criteria :(",
+            kind
+        );
+    }
+
+    let mut table = Table::init(rows);
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(titles);
+    table.print(writer)?;
+    write_conversion(writer, context)
+}
+
+fn write_conversion(writer: &mut dyn io::Write, context: &OutputContext) -> io::Result<()> {
+    match context.rates.map(|rates| rates.date) {
+        Some(Some(date)) => writeln!(
+            writer,
+            "Prices converted to {} with exchange rates from {}",
+            context.currency, date
+        ),
+        Some(None) => writeln!(
+            writer,
+            "Prices converted to {} with supplied exchange rates",
+            context.currency
+        ),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn journey_titles() -> Row {
+    row![
+        "Outbound - arrival (duration)",
+        "Inbound - arrival (duration)",
+        "Days",
+        "Trains",
+        "Class",
+        "Seats",
+        "Price",
+        "Per person"
+    ]
+}
+
+pub(crate) fn journey_row(journey: &TrainJourney, passengers: &Passengers) -> Row {
+    row![
+        format_leg(journey.outbound, journey.out_arrival, journey.out_duration),
+        format_leg(journey.inbound, journey.in_arrival, journey.in_duration),
+        journey.stay_length(),
+        format!(
+            "{} / {}",
+            format_train(&journey.out_details),
+            format_train(&journey.in_details)
+        ),
+        format!("{} / {}", journey.out_class, journey.in_class),
+        format!(
+            "{} / {}",
+            format_seats(journey.out_seats),
+            format_seats(journey.in_seats)
+        ),
+        journey.price,
+        format_fares(&journey.fares, passengers)
+    ]
+}
+
+pub(crate) fn market_titles(mut titles: Row) -> Row {
+    titles.add_cell(Cell::new("Market"));
+    titles.add_cell(Cell::new("Other markets"));
+    titles
+}
+
+pub(crate) fn market_row(mut row: Row, found: &MarketJourney) -> Row {
+    let others: Vec<String> = found
+        .prices
+        .iter()
+        .filter(|(market, _)| *market != found.market)
+        .map(|(market, price)| format!("{} {}", market, price))
+        .collect();
+
+    row.add_cell(Cell::new(&found.market.to_string()));
+    row.add_cell(Cell::new(&others.join(", ")));
+    row
+}
+
+pub(crate) fn one_way_titles() -> Row {
+    row![
+        "Departure - arrival (duration)",
+        "Train",
+        "Class",
+        "Seats",
+        "Price",
+        "Per person"
+    ]
+}
+
+pub(crate) fn one_way_row(train: &Train, passengers: &Passengers) -> Row {
+    row![
+        format_leg(train.departure, train.arrival, train.duration),
+        format_train(&train.details),
+        train.class,
+        format_seats(train.seats),
+        train.price,
+        format_fares(&train.fares, passengers)
+    ]
+}
+
+/// Columns of every leg, then days for return journeys, the price and the fare of each kind of
+/// passenger travelling
+fn delimited_titles(legs: &[&str], passengers: &Passengers) -> Row {
+    let mut titles = Row::empty();
+    for leg in legs.iter() {
+        for column in [
+            "date",
+            "departure",
+            "arrival date",
+            "arrival",
+            "duration",
+            "train",
+            "direct",
+            "class",
+            "seats",
+        ]
+        .iter()
+        {
+            titles.add_cell(Cell::new(&format!("{} {}", leg, column)));
+        }
+    }
+    if legs.len() > 1 {
+        titles.add_cell(Cell::new("Days"));
+    }
+    titles.add_cell(Cell::new("Price"));
+    titles.add_cell(Cell::new("Currency"));
+    for (name, count) in passenger_types(passengers).iter() {
+        if *count > 0 {
+            titles.add_cell(Cell::new(&format!("Fare {}", name)));
+        }
+    }
+    titles
+}
+
+fn delimited_row(journey: &TrainJourney, passengers: &Passengers) -> Row {
+    let mut row = Row::empty();
+    delimited_leg(
+        &mut row,
+        (journey.outbound, journey.out_arrival, journey.out_duration),
+        &journey.out_details,
+        journey.out_class,
+        journey.out_seats,
+    );
+    delimited_leg(
+        &mut row,
+        (journey.inbound, journey.in_arrival, journey.in_duration),
+        &journey.in_details,
+        journey.in_class,
+        journey.in_seats,
+    );
+    row.add_cell(Cell::new(&journey.stay_length().to_string()));
+    delimited_price(&mut row, journey.price, &journey.fares, passengers);
+    row
+}
+
+/// Arrival date is given too, as night trains and connections may arrive the next day, seats are
+/// left empty when the API doesn't say how many are left
+fn delimited_leg(
+    row: &mut Row,
+    (departure, arrival, duration): (NaiveDateTime, NaiveDateTime, Duration),
+    details: &TrainDetails,
+    class: FareClass,
+    seats: Option<i32>,
+) {
+    for value in [
+        departure.format(DELIMITED_DATE_FORMAT).to_string(),
+        departure.format(DELIMITED_TIME_FORMAT).to_string(),
+        arrival.format(DELIMITED_DATE_FORMAT).to_string(),
+        arrival.format(DELIMITED_TIME_FORMAT).to_string(),
+        format_duration(duration),
+        details.number.clone(),
+        if details.direct { "yes" } else { "no" }.to_string(),
+        class.to_string(),
+        seats.map(|seats| seats.to_string()).unwrap_or_default(),
+    ]
+    .iter()
+    {
+        row.add_cell(Cell::new(value));
+    }
+}
+
+fn delimited_price(row: &mut Row, price: Money, fares: &Fares, passengers: &Passengers) {
+    row.add_cell(Cell::new(&format!("{:.2}", price.amount)));
+    row.add_cell(Cell::new(&price.currency.to_string()));
+    for (_, fare) in fares_of(fares, passengers).iter() {
+        row.add_cell(Cell::new(&format!("{:.2}", fare.amount)));
+    }
+}
+
+fn format_leg(departure: NaiveDateTime, arrival: NaiveDateTime, duration: Duration) -> String {
+    format!(
+        "{} - {} ({}h{}m)",
+        departure.format(RESULT_DATETIME_FORMAT),
+        arrival.format(RESULT_ARRIVAL_FORMAT),
+        duration.num_hours(),
+        duration.num_minutes() % 60
+    )
+}
+
+/// Hours and minutes, which spreadsheets read as a duration
+fn format_duration(duration: Duration) -> String {
+    format!(
+        "{}:{:02}",
+        duration.num_hours(),
+        duration.num_minutes() % 60
+    )
+}
+
+fn format_train(details: &TrainDetails) -> String {
+    if details.direct {
+        details.number.clone()
+    } else {
+        format!("{} (connection)", details.number)
+    }
+}
+
+fn format_seats(seats: Option<i32>) -> String {
+    match seats {
+        Some(seats) if seats < LOW_AVAILABILITY_SEATS => format!("{} (low)", seats),
+        Some(seats) => seats.to_string(),
+        None => "?".to_string(),
+    }
+}
+
+fn format_fares(fares: &Fares, passengers: &Passengers) -> String {
+    fares_of(fares, passengers)
+        .iter()
+        .map(|(name, fare)| format!("{} {}", name, fare))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn passenger_types(passengers: &Passengers) -> [(&'static str, i16); 4] {
+    [
+        ("adult", passengers.adults),
+        ("child", passengers.children),
+        ("youth", passengers.youths),
+        ("senior", passengers.seniors),
+    ]
+}
+
+/// Fare of a single passenger of every type travelling
+fn fares_of(fares: &Fares, passengers: &Passengers) -> Vec<(&'static str, Money)> {
+    let fares = passengers.per_person(fares);
+    passenger_types(passengers)
+        .iter()
+        .zip([fares.adult, fares.child, fares.youth, fares.senior].iter())
+        .filter(|((_, count), _)| *count > 0)
+        .map(|((name, _), fare)| (*name, *fare))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::SearchQuery;
+    use crate::date::StayLengths;
+    use crate::market::Market;
+    use crate::money::Currency;
+    use crate::rates::ExchangeRates;
+    use crate::trains::{ClassPreference, Filter};
+    use chrono::NaiveDate;
+    use std::collections::HashMap;
+
+    fn gbp(amount: &str) -> Money {
+        Money::new(amount.parse().unwrap(), Currency::Gbp)
+    }
+
+    fn create_query() -> SearchQuery {
+        SearchQuery {
+            from: "London".to_string(),
+            to: "Paris".to_string(),
+            return_from: None,
+            return_to: None,
+            since: NaiveDate::from_ymd(2020, 4, 3),
+            until: NaiveDate::from_ymd(2020, 4, 10),
+            stay_lengths: StayLengths(vec![Duration::days(2)]),
+            weekdays: Vec::new(),
+            return_weekdays: Vec::new(),
+            passengers: Passengers {
+                adults: 2,
+                children: 0,
+                youths: 0,
+                seniors: 0,
+            },
+            class: ClassPreference::AnyCheapest,
+            filter: Filter::default(),
+        }
+    }
+
+    fn create_journey() -> TrainJourney {
+        let outbound = NaiveDate::from_ymd(2020, 4, 3).and_hms(18, 1, 0);
+        let inbound = NaiveDate::from_ymd(2020, 4, 5).and_hms(20, 33, 0);
+
+        TrainJourney {
+            outbound,
+            inbound,
+            out_arrival: outbound + Duration::minutes(196),
+            in_arrival: inbound + Duration::minutes(76),
+            price: gbp("157"),
+            fares: Fares {
+                adult: gbp("157"),
+                ..Fares::zero(Currency::Gbp)
+            },
+            out_duration: Duration::minutes(136),
+            in_duration: Duration::minutes(136),
+            out_class: FareClass::Standard,
+            in_class: FareClass::Plus,
+            out_seats: Some(50),
+            in_seats: None,
+            out_details: TrainDetails {
+                number: "9051".to_string(),
+                direct: true,
+                customs_checks: false,
+                dob_required: false,
+            },
+            in_details: TrainDetails {
+                number: "9058".to_string(),
+                direct: true,
+                customs_checks: false,
+                dob_required: false,
+            },
+        }
+    }
+
+    fn create_context<'a>(
+        query: &'a SearchQuery,
+        markets: &'a [Market],
+        rates: Option<&'a ExchangeRates>,
+    ) -> OutputContext<'a> {
+        OutputContext {
+            query,
+            currency: Currency::Gbp,
+            markets,
+            rates,
+            failures: &[],
+        }
+    }
+
+    #[test]
+    fn test_table_formatter() {
+        let query = create_query();
+        let markets = vec!["uk-en".parse().unwrap()];
+        let rates = ExchangeRates {
+            date: Some(NaiveDate::from_ymd(2020, 4, 1)),
+            base: Currency::Eur,
+            rates: HashMap::new(),
+        };
+
+        let mut output = Vec::new();
+        TableFormatter
+            .write(
+                &mut output,
+                &[create_journey()],
+                &create_context(&query, &markets, Some(&rates)),
+            )
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("| 2020-04-03 18:01 - 21:17 (2h16m) |"));
+        assert!(
+            output.contains("| 9051 / 9058 | Standard / Plus | 50 / ? | £157.00 | adult £78.50 |")
+        );
+        assert!(output.ends_with("Prices converted to GBP with exchange rates from 2020-04-01\n"));
+
+        let mut output = Vec::new();
+        TableFormatter
+            .write_trains(&mut output, &[], &create_context(&query, &markets, None))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "There was no train matching supplied criteria :(\n"
+        );
+    }
+
+    #[test]
+    fn test_delimited_formatter() {
+        let query = create_query();
+        let markets: Vec<Market> = vec!["uk-en".parse().unwrap(), "fr-fr".parse().unwrap()];
+        let found = MarketJourney {
+            journey: create_journey(),
+            market: markets[1].clone(),
+            prices: vec![(markets[1].clone(), gbp("157"))],
+        };
+
+        let mut output = Vec::new();
+        DelimitedFormatter { delimiter: b'\t' }
+            .write_markets(
+                &mut output,
+                &[found],
+                &create_context(&query, &markets, None),
+            )
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<Vec<&str>> = output
+            .lines()
+            .map(|line| line.split('\t').collect())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0][..9],
+            [
+                "Outbound date",
+                "Outbound departure",
+                "Outbound arrival date",
+                "Outbound arrival",
+                "Outbound duration",
+                "Outbound train",
+                "Outbound direct",
+                "Outbound class",
+                "Outbound seats"
+            ]
+        );
+        assert_eq!(
+            lines[0][18..],
+            [
+                "Days",
+                "Price",
+                "Currency",
+                "Fare adult",
+                "Market",
+                "Price uk-en",
+                "Price fr-fr"
+            ]
+        );
+        assert_eq!(
+            lines[1],
+            vec![
+                "2020-04-03",
+                "18:01",
+                "2020-04-03",
+                "21:17",
+                "2:16",
+                "9051",
+                "yes",
+                "Standard",
+                "50",
+                "2020-04-05",
+                "20:33",
+                "2020-04-05",
+                "21:49",
+                "2:16",
+                "9058",
+                "yes",
+                "Plus",
+                "",
+                "3",
+                "157.00",
+                "GBP",
+                "78.50",
+                "fr-fr",
+                "",
+                "157.00",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delimited_formatter_trains() {
+        let query = create_query();
+        let markets = vec!["uk-en".parse().unwrap()];
+        let journey = create_journey();
+        let train = Train {
+            departure: journey.outbound,
+            // Connections may arrive the next day
+            arrival: NaiveDate::from_ymd(2020, 4, 4).and_hms(0, 12, 0),
+            duration: Duration::minutes(311),
+            price: gbp("79"),
+            fares: Fares {
+                adult: gbp("79"),
+                ..Fares::zero(Currency::Gbp)
+            },
+            class: FareClass::Standard,
+            seats: Some(3),
+            details: TrainDetails {
+                direct: false,
+                ..journey.out_details
+            },
+        };
+
+        let mut output = Vec::new();
+        DelimitedFormatter { delimiter: b',' }
+            .write_trains(
+                &mut output,
+                &[train],
+                &create_context(&query, &markets, None),
+            )
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(
+            output.lines().collect::<Vec<_>>(),
+            vec![
+                "Outbound date,Outbound departure,Outbound arrival date,Outbound arrival,\
+                 Outbound duration,Outbound train,Outbound direct,Outbound class,Outbound seats,\
+                 Price,Currency,Fare adult",
+                "2020-04-03,18:01,2020-04-04,00:12,5:11,9051,no,Standard,3,79.00,GBP,39.50",
+            ]
+        );
+    }
+}
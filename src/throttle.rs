@@ -0,0 +1,96 @@
+use std::cmp;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::{self, Instant};
+
+/// How hard we are allowed to hit the Eurostar API
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RequestLimits {
+    /// Maximum number of requests in flight at the same time
+    pub max_concurrent: usize,
+    /// Maximum number of requests started per second, `None` means no limit
+    pub per_second: Option<f32>,
+}
+
+impl Default for RequestLimits {
+    fn default() -> RequestLimits {
+        RequestLimits {
+            max_concurrent: 4,
+            per_second: Some(5.0),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Throttle {
+    semaphore: Semaphore,
+    interval: Option<Duration>,
+    next_slot: Mutex<Instant>,
+}
+
+impl Throttle {
+    pub(crate) fn new(limits: RequestLimits) -> Throttle {
+        Throttle {
+            // Zero permits would block forever
+            semaphore: Semaphore::new(cmp::max(limits.max_concurrent, 1)),
+            interval: limits
+                .per_second
+                .filter(|per_second| *per_second > 0.0)
+                .map(|per_second| Duration::from_secs_f32(1.0 / per_second)),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits until another request may be sent, it counts as in flight until the permit is dropped
+    pub(crate) async fn acquire(&self) -> SemaphorePermit<'_> {
+        let permit = self.semaphore.acquire().await;
+
+        if let Some(interval) = self.interval {
+            let slot = {
+                let mut next_slot = self.next_slot.lock().unwrap();
+                let slot = cmp::max(*next_slot, Instant::now());
+                *next_slot = slot + interval;
+                slot
+            };
+            time::delay_until(slot).await;
+        }
+        permit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+
+    #[tokio::test]
+    async fn test_acquire_spaces_out_requests() {
+        let throttle = Throttle::new(RequestLimits {
+            max_concurrent: 10,
+            per_second: Some(20.0),
+        });
+        let start = Instant::now();
+
+        future::join_all((0..5).map(|_| throttle.acquire())).await;
+
+        // First request goes straight away, the other four wait 50ms each
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_acquire_limits_concurrency() {
+        let throttle = Throttle::new(RequestLimits {
+            max_concurrent: 2,
+            per_second: None,
+        });
+
+        let first = throttle.acquire().await;
+        let _second = throttle.acquire().await;
+        assert_eq!(throttle.semaphore.available_permits(), 0);
+
+        drop(first);
+        let _third = throttle.acquire().await;
+        assert_eq!(throttle.semaphore.available_permits(), 0);
+    }
+}
@@ -1,71 +1,492 @@
 use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
 use futures::future;
-use log::{debug, error, trace, warn};
+use log::{debug, error, info, trace, warn};
 use maplit::hashmap;
-use reqwest::{Client, Error, Response, StatusCode};
+use reqwest::{Client, Error, StatusCode};
+use rust_decimal::Decimal;
 use serde::Deserialize;
-use serde_json;
-use std::collections::HashMap;
-
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::time;
+
+use crate::cache::{Cache, CacheKind};
+use crate::client::ClientConfig;
 use crate::date;
+use crate::money::{Currency, Money};
+use crate::recording::{self, Recording};
+use crate::retry::{self, RetryPolicy};
+use crate::throttle::Throttle;
 
-#[cfg(test)]
-use mockito;
-
-static EUROSTAR_URL: &str = "https://api.prod.eurostar.com/bpa";
-static SEARCH_LOCATION: &str = "train-search/uk-en";
-static STATIONS_LOCATION: &str = "hotels-search/regions/uk-en";
+static SEARCH_LOCATION: &str = "train-search";
+static STATIONS_LOCATION: &str = "hotels-search/regions";
 static API_KEY_HEADER: &str = "x-apikey";
 
-#[derive(Debug, PartialEq)]
+/// Everything needed to talk to the Eurostar API, shared by all requests of a search
+#[derive(Debug)]
+pub(crate) struct Api {
+    client: Client,
+    api_key: String,
+    base_url: String,
+    /// Market and language, e.g. `uk-en` or `fr-fr`
+    locale: String,
+    /// Prices are given in the currency of the market
+    currency: Currency,
+    throttle: Throttle,
+    retry: RetryPolicy,
+    cache: Option<Cache>,
+    recording: Option<Recording>,
+}
+
+impl Api {
+    pub(crate) fn new(api_key: &str, config: &ClientConfig) -> Api {
+        let base_url = config.base_url.trim_end_matches('/').to_string();
+        Api {
+            client: Client::new(),
+            api_key: api_key.to_string(),
+            locale: format!("{}-{}", config.market, config.language),
+            currency: Currency::from_market(&config.market),
+            throttle: Throttle::new(config.limits),
+            retry: config.retry,
+            cache: config
+                .cache
+                .clone()
+                .map(|cache| Cache::new(cache, &base_url)),
+            recording: config.recording.clone(),
+            base_url,
+        }
+    }
+
+    pub(crate) fn currency(&self) -> Currency {
+        self.currency
+    }
+}
+
+/// Return journey made of an outbound and an inbound train, `out_*` fields describe the former and
+/// `in_*` ones the latter
+#[derive(Debug, PartialEq, Clone)]
 pub struct TrainJourney {
+    /// Departure of the outbound train, in local time of its station like every time here
     pub outbound: NaiveDateTime,
+    /// Departure of the inbound train
     pub inbound: NaiveDateTime,
-    pub price: f32,
+    pub out_arrival: NaiveDateTime,
+    pub in_arrival: NaiveDateTime,
+    /// Both trains for all passengers
+    pub price: Money,
+    /// Both trains for each passenger type
+    pub fares: Fares,
     pub out_duration: Duration,
     pub in_duration: Duration,
+    pub out_class: FareClass,
+    pub in_class: FareClass,
+    /// Seats left in the class, `None` when the API doesn't say
+    pub out_seats: Option<i32>,
+    pub in_seats: Option<i32>,
+    pub out_details: TrainDetails,
+    pub in_details: TrainDetails,
 }
 
+impl TrainJourney {
+    /// Number of days spent between outbound and inbound journeys, counting both of them
+    pub fn stay_length(&self) -> i64 {
+        (self.inbound.date() - self.outbound.date()).num_days() + 1
+    }
+
+    pub fn in_currency(self, currency: Currency, rate: Decimal) -> TrainJourney {
+        TrainJourney {
+            price: self.price.in_currency(currency, rate),
+            fares: self.fares.in_currency(currency, rate),
+            ..self
+        }
+    }
+}
+
+/// Why a request to the Eurostar API failed
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum QueryError {
+    /// Network error, or the response couldn't be read
     ReqwestError(Error),
+    /// Response wasn't the JSON it was expected to be
     JsonParseError(String),
+    /// Request was refused (e.g. a wrong API key), the API returned nothing useful or a replayed
+    /// response is missing
     InternalError(String),
+    /// Server kept failing even after retrying
+    ServerError(String),
+}
+
+impl error::Error for QueryError {}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryError::ReqwestError(err) => write!(f, "{}", err),
+            QueryError::JsonParseError(msg)
+            | QueryError::InternalError(msg)
+            | QueryError::ServerError(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// Trains for these dates couldn't be fetched, `inbound` is `None` for one-way searches
+#[derive(Debug)]
+pub struct DateFailure {
+    pub outbound: NaiveDate,
+    pub inbound: Option<NaiveDate>,
+    /// Shared by all date pairs which depended on the same failed request
+    pub error: Arc<QueryError>,
+}
+
+impl fmt::Display for DateFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.inbound {
+            Some(inbound) => write!(f, "{} - {}: {}", self.outbound, inbound, self.error),
+            None => write!(f, "{}: {}", self.outbound, self.error),
+        }
+    }
+}
+
+/// Whatever could be found, along with dates for which the search failed
+#[derive(Debug)]
+pub struct SearchResults<T> {
+    pub results: Vec<T>,
+    pub failures: Vec<DateFailure>,
+}
+
+impl<T> Default for SearchResults<T> {
+    fn default() -> SearchResults<T> {
+        SearchResults {
+            results: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+}
+
+/// Class of travel, from the cheapest one
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum FareClass {
+    Standard,
+    Plus,
+    Premier,
+}
+
+impl FareClass {
+    /// Classes are always listed in the same order by the API
+    fn from_index(index: usize) -> Option<FareClass> {
+        match index {
+            0 => Some(FareClass::Standard),
+            1 => Some(FareClass::Plus),
+            2 => Some(FareClass::Premier),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FareClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FareClass::Standard => write!(f, "Standard"),
+            FareClass::Plus => write!(f, "Plus"),
+            FareClass::Premier => write!(f, "Premier"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ClassPreference {
+    Only(FareClass),
+    AnyCheapest,
+}
+
+impl fmt::Display for ClassPreference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClassPreference::Only(class) => write!(f, "{}", class.to_string().to_lowercase()),
+            ClassPreference::AnyCheapest => write!(f, "any-cheapest"),
+        }
+    }
+}
+
+impl FromStr for ClassPreference {
+    type Err = String;
+
+    fn from_str(class: &str) -> Result<ClassPreference, String> {
+        match class.to_lowercase().as_str() {
+            "standard" => Ok(ClassPreference::Only(FareClass::Standard)),
+            "plus" => Ok(ClassPreference::Only(FareClass::Plus)),
+            "premier" => Ok(ClassPreference::Only(FareClass::Premier)),
+            "any-cheapest" => Ok(ClassPreference::AnyCheapest),
+            class => Err(format!("{} is an invalid class name!", class)),
+        }
+    }
+}
+
+/// Train as described by the API, independently of the class and fares
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct TrainDetails {
+    /// E.g. `9051`
+    #[serde(rename = "id")]
+    pub number: String,
+    /// False when a change of trains is needed on the way
+    #[serde(default)]
+    pub direct: bool,
+    /// Passengers go through customs before boarding
+    #[serde(default, rename = "requiresCustomsChecks")]
+    pub customs_checks: bool,
+    /// Dates of birth of passengers are needed to book it
+    #[serde(default, rename = "dobRequired")]
+    pub dob_required: bool,
+}
+
+/// Price for each passenger type, for all passengers of that type as the API gives it (e.g. `adult`
+/// is the fare of every adult in the party)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Fares {
+    pub adult: Money,
+    pub child: Money,
+    pub youth: Money,
+    pub senior: Money,
+}
+
+impl Fares {
+    pub fn zero(currency: Currency) -> Fares {
+        Fares {
+            adult: Money::zero(currency),
+            child: Money::zero(currency),
+            youth: Money::zero(currency),
+            senior: Money::zero(currency),
+        }
+    }
+
+    pub fn in_currency(self, currency: Currency, rate: Decimal) -> Fares {
+        Fares {
+            adult: self.adult.in_currency(currency, rate),
+            child: self.child.in_currency(currency, rate),
+            youth: self.youth.in_currency(currency, rate),
+            senior: self.senior.in_currency(currency, rate),
+        }
+    }
+
+    /// Fares of both trains of a journey, `None` if they are in different currencies
+    fn checked_add(self, other: Fares) -> Option<Fares> {
+        Some(Fares {
+            adult: self.adult.checked_add(other.adult)?,
+            child: self.child.checked_add(other.child)?,
+            youth: self.youth.checked_add(other.youth)?,
+            senior: self.senior.checked_add(other.senior)?,
+        })
+    }
+}
+
+/// How many passengers of each type travel, see `--help` for their ages
+#[derive(Debug)]
+pub struct Passengers {
+    /// Always sent to the API, unlike the others which are left out when zero
+    pub adults: i16,
+    pub children: i16,
+    pub youths: i16,
+    pub seniors: i16,
+}
+
+impl Passengers {
+    pub fn count(&self) -> i32 {
+        i32::from(self.adults)
+            + i32::from(self.children)
+            + i32::from(self.youths)
+            + i32::from(self.seniors)
+    }
+
+    /// Fare of a single passenger of each type, zero for types nobody in the party belongs to
+    pub fn per_person(&self, fares: &Fares) -> Fares {
+        let share = |fare: Money, count: i16| {
+            if count > 0 {
+                Money::new(
+                    (fare.amount / Decimal::from(count)).round_dp(2),
+                    fare.currency,
+                )
+            } else {
+                Money::zero(fare.currency)
+            }
+        };
+        Fares {
+            adult: share(fares.adult, self.adults),
+            child: share(fares.child, self.children),
+            youth: share(fares.youth, self.youths),
+            senior: share(fares.senior, self.seniors),
+        }
+    }
+
+    fn query_params(&self) -> HashMap<&'static str, String> {
+        let mut params = hashmap! {
+            "adult" => self.adults.to_string(),
+        };
+        for (param, count) in [
+            ("child", self.children),
+            ("youth", self.youths),
+            ("senior", self.seniors),
+        ]
+        .iter()
+        {
+            if *count > 0 {
+                params.insert(param, count.to_string());
+            }
+        }
+        params
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Itinerary {
+    pub from: i32,
+    pub to: i32,
+    pub return_from: i32,
+    pub return_to: i32,
+}
+
+impl Itinerary {
+    fn is_open_jaw(&self) -> bool {
+        self.return_from != self.to || self.return_to != self.from
+    }
 }
 
+/// Which journeys are kept, the default one keeps all of them
+///
+/// Times are local times of the station and are compared strictly, a train departing at 18:00
+/// matches neither `out_departure_after` nor `out_departure_before` set to 18:00.
+#[derive(Debug, Default)]
 pub struct Filter {
-    pub max_price: Option<f32>,
+    /// Highest price for all passengers and both trains, which is still kept, in the currency of
+    /// the market searched in
+    pub max_price: Option<Money>,
+    /// Outbound train has to depart later than this
     pub out_departure_after: Option<NaiveTime>,
+    /// Outbound train has to depart earlier than this
     pub out_departure_before: Option<NaiveTime>,
     pub in_departure_after: Option<NaiveTime>,
     pub in_departure_before: Option<NaiveTime>,
+    /// Outbound train has to arrive later than this
+    pub out_arrival_after: Option<NaiveTime>,
+    /// Outbound train has to arrive earlier than this
+    pub out_arrival_before: Option<NaiveTime>,
+    pub in_arrival_after: Option<NaiveTime>,
+    pub in_arrival_before: Option<NaiveTime>,
+    /// Fewest seats left in the class of each train, trains the API gives no count for are kept
+    pub min_seats: Option<i32>,
+    /// Leave out trains needing a change
+    pub direct_only: bool,
 }
 
-#[derive(Debug)]
-struct Train {
-    departure: NaiveDateTime,
-    duration: Duration,
-    price: f32,
+impl Filter {
+    fn matches_price(&self, price: Money) -> bool {
+        self.max_price.map_or(true, |max_price| price <= max_price)
+    }
+
+    fn matches_outbound(&self, train: &Train) -> bool {
+        self.matches_train(train)
+            && matches_time(
+                train.departure,
+                self.out_departure_after,
+                self.out_departure_before,
+            )
+            && matches_time(
+                train.arrival,
+                self.out_arrival_after,
+                self.out_arrival_before,
+            )
+    }
+
+    fn matches_inbound(&self, train: &Train) -> bool {
+        self.matches_train(train)
+            && matches_time(
+                train.departure,
+                self.in_departure_after,
+                self.in_departure_before,
+            )
+            && matches_time(train.arrival, self.in_arrival_after, self.in_arrival_before)
+    }
+
+    /// Trains without seat information are kept, API does not always return it
+    fn matches_train(&self, train: &Train) -> bool {
+        let enough_seats = match (self.min_seats, train.seats) {
+            (Some(min_seats), Some(seats)) => seats >= min_seats,
+            _ => true,
+        };
+        enough_seats && (!self.direct_only || train.details.direct)
+    }
+}
+
+fn matches_time(
+    datetime: NaiveDateTime,
+    after: Option<NaiveTime>,
+    before: Option<NaiveTime>,
+) -> bool {
+    let time = datetime.time();
+    after.map_or(true, |after| time > after) && before.map_or(true, |before| time < before)
+}
+
+/// Train priced in a single class, as found by one-way searches
+#[derive(Debug, PartialEq)]
+pub struct Train {
+    /// In local time of the station
+    pub departure: NaiveDateTime,
+    /// In local time of the station
+    pub arrival: NaiveDateTime,
+    pub duration: Duration,
+    /// For all passengers
+    pub price: Money,
+    /// For each passenger type
+    pub fares: Fares,
+    pub class: FareClass,
+    /// Seats left in the class, `None` when the API doesn't say
+    pub seats: Option<i32>,
+    pub details: TrainDetails,
+}
+
+impl Train {
+    pub fn in_currency(self, currency: Currency, rate: Decimal) -> Train {
+        Train {
+            price: self.price.in_currency(currency, rate),
+            fares: self.fares.in_currency(currency, rate),
+            ..self
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
 struct Price {
     #[serde(default)]
-    adult: f32,
+    adult: Decimal,
+    #[serde(default)]
+    child: Decimal,
+    #[serde(default)]
+    youth: Decimal,
+    #[serde(default)]
+    senior: Decimal,
 }
 
 #[derive(Deserialize, Debug)]
 struct Class {
     price: Option<Price>,
+    #[serde(default, rename = "isNotAvailable")]
+    is_not_available: bool,
+    remaining: Option<i32>,
 }
 
 #[derive(Deserialize, Debug)]
 struct Journey {
     #[serde(with = "date::naive_time", rename = "departureTime")]
     departure_time: NaiveTime,
+    #[serde(with = "date::naive_time", rename = "arrivalTime")]
+    arrival_time: NaiveTime,
     #[serde(with = "date::duration")]
     duration: Duration,
     class: Vec<Class>,
+    #[serde(flatten)]
+    details: TrainDetails,
 }
 
 #[derive(Deserialize, Debug)]
@@ -93,28 +514,26 @@ struct StationsResponseJson {
     extra: HashMap<String, Station>,
 }
 
-fn filter_journeys(trains: &(Vec<Train>, Vec<Train>), filter: &Filter) -> Vec<TrainJourney> {
+fn filter_journeys(
+    out_trains: &[Train],
+    in_trains: &[Train],
+    filter: &Filter,
+) -> Vec<TrainJourney> {
     let mut res = Vec::new();
 
-    for out_t in trains.0.iter() {
-        for in_t in trains.1.iter() {
-            let total_price = out_t.price + in_t.price;
-            if filter.max_price.is_some() && total_price > filter.max_price.unwrap() {
-                continue;
-            } else if filter.out_departure_after.is_some()
-                && out_t.departure.time() <= filter.out_departure_after.unwrap()
-            {
-                continue;
-            } else if filter.out_departure_before.is_some()
-                && out_t.departure.time() >= filter.out_departure_before.unwrap()
-            {
-                continue;
-            } else if filter.in_departure_after.is_some()
-                && in_t.departure.time() <= filter.in_departure_after.unwrap()
-            {
-                continue;
-            } else if filter.in_departure_before.is_some()
-                && in_t.departure.time() >= filter.in_departure_before.unwrap()
+    for out_t in out_trains.iter() {
+        for in_t in in_trains.iter() {
+            // Never `None` as trains of a search are priced in the currency of its market
+            let (total_price, fares) = match (
+                out_t.price.checked_add(in_t.price),
+                out_t.fares.checked_add(in_t.fares),
+            ) {
+                (Some(total_price), Some(fares)) => (total_price, fares),
+                _ => continue,
+            };
+            if !filter.matches_price(total_price)
+                || !filter.matches_outbound(out_t)
+                || !filter.matches_inbound(in_t)
             {
                 continue;
             }
@@ -122,18 +541,40 @@ fn filter_journeys(trains: &(Vec<Train>, Vec<Train>), filter: &Filter) -> Vec<Tr
             res.push(TrainJourney {
                 outbound: out_t.departure,
                 inbound: in_t.departure,
+                out_arrival: out_t.arrival,
+                in_arrival: in_t.arrival,
                 price: total_price,
+                fares,
                 out_duration: out_t.duration,
                 in_duration: in_t.duration,
+                out_class: out_t.class,
+                in_class: in_t.class,
+                out_seats: out_t.seats,
+                in_seats: in_t.seats,
+                out_details: out_t.details.clone(),
+                in_details: in_t.details.clone(),
             })
         }
     }
     res
 }
 
-pub async fn get_stations_map(api_key: &str) -> Result<HashMap<String, i32>, QueryError> {
-    let client = Client::new();
-    let response = match do_request(&client, STATIONS_LOCATION, api_key, hashmap! {}).await? {
+fn filter_one_way_trains(trains: Vec<Train>, filter: &Filter) -> Vec<Train> {
+    trains
+        .into_iter()
+        .filter(|train| filter.matches_price(train.price) && filter.matches_outbound(train))
+        .collect()
+}
+
+pub(crate) async fn get_stations_map(api: &Api) -> Result<HashMap<String, i32>, QueryError> {
+    let text = match do_request(
+        api,
+        &format!("{}/{}", STATIONS_LOCATION, api.locale),
+        hashmap! {},
+        CacheKind::Stations,
+    )
+    .await?
+    {
         Some(res) => res,
         None => {
             return Err(QueryError::InternalError(
@@ -142,8 +583,6 @@ pub async fn get_stations_map(api_key: &str) -> Result<HashMap<String, i32>, Que
         }
     };
 
-    let text = response.text().await.map_err(QueryError::ReqwestError)?;
-
     let json: StationsResponseJson = match serde_json::from_str(&text) {
         Ok(res) => res,
         Err(err) => {
@@ -171,119 +610,348 @@ pub async fn get_stations_map(api_key: &str) -> Result<HashMap<String, i32>, Que
     Ok(stations)
 }
 
-pub async fn get_journeys(
-    travels: &Vec<(NaiveDate, NaiveDate)>,
-    api_key: &str,
+pub(crate) async fn get_journeys(
+    travels: &[(NaiveDate, NaiveDate)],
+    api: &Api,
+    itinerary: &Itinerary,
+    passengers: &Passengers,
+    class: ClassPreference,
+    filter: &Filter,
+) -> SearchResults<TrainJourney> {
+    let plan = plan_requests(travels, !itinerary.is_open_jaw());
+
+    // Without deduplication every pair needs a request, or two of them for open-jaw itineraries
+    let naive_count = if itinerary.is_open_jaw() {
+        2 * travels.len()
+    } else {
+        travels.len()
+    };
+    info!(
+        "Sending {} requests for {} date pairs, saved {} requests",
+        plan.count(),
+        travels.len(),
+        naive_count - plan.count()
+    );
+
+    let (combined, outbound, inbound) = future::join3(
+        future::join_all(plan.combined.iter().map(|(outbound_date, inbound_date)| {
+            get_trains(
+                api,
+                itinerary.from,
+                itinerary.to,
+                *outbound_date,
+                Some(*inbound_date),
+                passengers,
+                class,
+            )
+        })),
+        future::join_all(plan.outbound.iter().map(|outbound_date| {
+            get_trains(
+                api,
+                itinerary.from,
+                itinerary.to,
+                *outbound_date,
+                None,
+                passengers,
+                class,
+            )
+        })),
+        future::join_all(plan.inbound.iter().map(|inbound_date| {
+            get_trains(
+                api,
+                itinerary.return_from,
+                itinerary.return_to,
+                *inbound_date,
+                None,
+                passengers,
+                class,
+            )
+        })),
+    )
+    .await;
+
+    // A failed request spoils every pair using one of its dates, so errors are shared
+    let mut out_trains = HashMap::new();
+    let mut in_trains = HashMap::new();
+
+    for ((outbound_date, inbound_date), res) in plan.combined.iter().zip(combined) {
+        match res {
+            Ok((out_t, in_t)) => {
+                out_trains.insert(*outbound_date, Ok(out_t));
+                in_trains.insert(*inbound_date, Ok(in_t));
+            }
+            Err(err) => {
+                let err = Arc::new(err);
+                out_trains.insert(*outbound_date, Err(err.clone()));
+                in_trains.insert(*inbound_date, Err(err));
+            }
+        }
+    }
+    for (outbound_date, res) in plan.outbound.iter().zip(outbound) {
+        out_trains.insert(*outbound_date, res.map(|res| res.0).map_err(Arc::new));
+    }
+    for (inbound_date, res) in plan.inbound.iter().zip(inbound) {
+        in_trains.insert(*inbound_date, res.map(|res| res.0).map_err(Arc::new));
+    }
+
+    let mut results = SearchResults::default();
+
+    for (outbound_date, inbound_date) in travels.iter() {
+        match (&out_trains[outbound_date], &in_trains[inbound_date]) {
+            (Ok(out_t), Ok(in_t)) => {
+                results
+                    .results
+                    .append(&mut filter_journeys(out_t, in_t, filter));
+            }
+            (Err(err), _) | (_, Err(err)) => results.failures.push(DateFailure {
+                outbound: *outbound_date,
+                inbound: Some(*inbound_date),
+                error: err.clone(),
+            }),
+        }
+    }
+    results
+}
+
+/// Distinct dates to fetch trains for, a single request returns trains for one outbound and one
+/// inbound date so they are paired up where possible and assembled into journeys afterwards
+#[derive(Debug, PartialEq)]
+struct RequestPlan {
+    combined: Vec<(NaiveDate, NaiveDate)>,
+    outbound: Vec<NaiveDate>,
+    inbound: Vec<NaiveDate>,
+}
+
+impl RequestPlan {
+    fn count(&self) -> usize {
+        self.combined.len() + self.outbound.len() + self.inbound.len()
+    }
+}
+
+fn plan_requests(travels: &[(NaiveDate, NaiveDate)], combine: bool) -> RequestPlan {
+    let mut outbound: Vec<NaiveDate> = travels.iter().map(|travel| travel.0).collect();
+    let mut inbound: Vec<NaiveDate> = travels.iter().map(|travel| travel.1).collect();
+    for dates in [&mut outbound, &mut inbound].iter_mut() {
+        dates.sort();
+        dates.dedup();
+    }
+
+    if !combine {
+        return RequestPlan {
+            combined: Vec::new(),
+            outbound,
+            inbound,
+        };
+    }
+
+    // API expects inbound date not to be before the outbound one, pair every inbound date with
+    // the earliest outbound date which wasn't used yet
+    let mut combined = Vec::new();
+    let mut unpaired_inbound = Vec::new();
+    let mut outbound = outbound.into_iter().peekable();
+    let mut available = VecDeque::new();
+
+    for inbound_date in inbound {
+        while let Some(outbound_date) = outbound.next_if(|date| *date <= inbound_date) {
+            available.push_back(outbound_date);
+        }
+        match available.pop_front() {
+            Some(outbound_date) => combined.push((outbound_date, inbound_date)),
+            None => unpaired_inbound.push(inbound_date),
+        }
+    }
+    available.extend(outbound);
+
+    RequestPlan {
+        combined,
+        outbound: available.into_iter().collect(),
+        inbound: unpaired_inbound,
+    }
+}
+
+pub(crate) async fn get_one_way_trains(
+    dates: &[NaiveDate],
+    api: &Api,
     from: i32,
     to: i32,
-    adults: i16,
+    passengers: &Passengers,
+    class: ClassPreference,
     filter: &Filter,
-) -> Result<Vec<TrainJourney>, QueryError> {
-    let client = Client::new();
+) -> SearchResults<Train> {
     let mut all_trains = Vec::new();
 
-    for (outbound_date, inbound_date) in travels.iter() {
+    for outbound_date in dates.iter() {
         all_trains.push(get_trains(
-            &client,
-            api_key,
+            api,
             from,
             to,
             *outbound_date,
-            *inbound_date,
-            adults,
+            None,
+            passengers,
+            class,
         ));
     }
 
-    let mut journeys = Vec::new();
-
-    for trains in future::join_all(all_trains).await {
-        journeys.append(&mut filter_journeys(&trains?, filter));
+    let mut results = SearchResults::default();
+
+    for (outbound_date, res) in dates.iter().zip(future::join_all(all_trains).await) {
+        match res {
+            Ok((out_t, _)) => results
+                .results
+                .append(&mut filter_one_way_trains(out_t, filter)),
+            Err(err) => results.failures.push(DateFailure {
+                outbound: *outbound_date,
+                inbound: None,
+                error: Arc::new(err),
+            }),
+        }
     }
-    Ok(journeys)
+    results
 }
 
 async fn get_trains(
-    client: &Client,
-    api_key: &str,
+    api: &Api,
     from: i32,
     to: i32,
     since: NaiveDate,
-    until: NaiveDate,
-    adults: i16,
+    until: Option<NaiveDate>,
+    passengers: &Passengers,
+    class: ClassPreference,
 ) -> Result<(Vec<Train>, Vec<Train>), QueryError> {
+    let mut query_params = passengers.query_params();
+    query_params.insert("outbound-date", format_date(since));
+    if let Some(until) = until {
+        query_params.insert("inbound-date", format_date(until));
+    }
+
     let response = do_request(
-        client,
-        &format!("{}/{}/{}", SEARCH_LOCATION, from, to),
-        api_key,
-        hashmap! {
-            "outbound-date" => format_date(since),
-            "inbound-date" => format_date(until),
-            "adult" => adults.to_string(),
-        },
+        api,
+        &format!("{}/{}/{}/{}", SEARCH_LOCATION, api.locale, from, to),
+        query_params,
+        CacheKind::Trains,
     )
     .await?;
 
-    let trains = parse_response(response, since, until).await;
-
-    trains
+    parse_response(response, since, until, class, api.currency)
 }
 
+/// Body of the response, `None` if there is nothing to parse
 async fn do_request(
-    client: &Client,
+    api: &Api,
     location: &str,
-    api_key: &str,
     query_params: HashMap<&str, String>,
-) -> Result<Option<Response>, QueryError> {
-    #[cfg(test)]
-    let url = &mockito::server_url();
-    #[cfg(not(test))]
-    let url = EUROSTAR_URL;
-
-    let request = client
-        .get(&format!("{}/{}", url, location))
-        .query(&query_params)
-        .header(API_KEY_HEADER, api_key);
-
-    debug!("Prepared request: {:?}", request);
-
-    let response = request.send().await.map_err(QueryError::ReqwestError)?;
-
-    let status = response.status();
-
-    if status == StatusCode::UNPROCESSABLE_ENTITY {
-        return Ok(None);
-    }
-
-    if status.is_client_error() {
-        return Err(QueryError::InternalError(format!(
-            "Got {} response for {}: {}",
-            status,
-            response.url().to_string(),
-            response.text().await.unwrap_or("".to_string()),
-        )));
-    } else if status.is_server_error() {
-        error!(
-            "Got {} response for {}: {}",
-            status,
-            response.url().to_string(),
-            response.text().await.unwrap_or("".to_string()),
+    cache_kind: CacheKind,
+) -> Result<Option<String>, QueryError> {
+    if let Some(Recording::Replay(dir)) = &api.recording {
+        return recording::replay(dir, location, &query_params);
+    }
+
+    let body = fetch(api, location, &query_params, cache_kind).await?;
+
+    if let Some(Recording::Record(dir)) = &api.recording {
+        recording::record(dir, location, &query_params, body.as_deref());
+    }
+    Ok(body)
+}
+
+async fn fetch(
+    api: &Api,
+    location: &str,
+    query_params: &HashMap<&str, String>,
+    cache_kind: CacheKind,
+) -> Result<Option<String>, QueryError> {
+    if let Some(body) = api
+        .cache
+        .as_ref()
+        .and_then(|cache| cache.get(cache_kind, location, query_params))
+    {
+        return Ok(Some(body));
+    }
+
+    let mut attempt = 1;
+    loop {
+        let request = api
+            .client
+            .get(&format!("{}/{}", api.base_url, location))
+            .query(query_params)
+            .header(API_KEY_HEADER, api.api_key.as_str());
+
+        debug!("Prepared request: {:?}", request);
+
+        // Held until the whole body is read
+        let permit = api.throttle.acquire().await;
+
+        let (err, retry_after) = match request.send().await {
+            Ok(response) => {
+                let status = response.status();
+                let url = response.url().clone();
+
+                if status == StatusCode::UNPROCESSABLE_ENTITY {
+                    return Ok(None);
+                }
+
+                if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                    let retry_after = retry::retry_after(response.headers());
+                    let err = QueryError::ServerError(format!(
+                        "Got {} response for {}: {}",
+                        status,
+                        url,
+                        response.text().await.unwrap_or_default(),
+                    ));
+                    (err, retry_after)
+                } else if status.is_client_error() {
+                    return Err(QueryError::InternalError(format!(
+                        "Got {} response for {}: {}",
+                        status,
+                        url,
+                        response.text().await.unwrap_or_default(),
+                    )));
+                } else {
+                    debug!("Got {} response for {}", status, url);
+                    match response.text().await {
+                        Ok(text) => {
+                            if let Some(cache) = &api.cache {
+                                cache.put(location, query_params, &text);
+                            }
+                            return Ok(Some(text));
+                        }
+                        Err(err) => (QueryError::ReqwestError(err), None),
+                    }
+                }
+            }
+            Err(err) => (QueryError::ReqwestError(err), None),
+        };
+        drop(permit);
+
+        if attempt >= api.retry.max_attempts {
+            error!("{}, giving up after {} attempts", err, attempt);
+            return Err(err);
+        }
+
+        let delay = api.retry.delay(attempt, retry_after);
+        warn!(
+            "{}, retrying in {}ms (attempt {} of {})",
+            err,
+            delay.as_millis(),
+            attempt,
+            api.retry.max_attempts
         );
-        return Ok(None);
-    } else {
-        debug!("Got {} response for {}", status, response.url().to_string());
+        time::delay_for(delay).await;
+        attempt += 1;
     }
-    Ok(Some(response))
 }
 
-async fn parse_response(
-    response: Option<Response>,
+fn parse_response(
+    response: Option<String>,
     out_date: NaiveDate,
-    in_date: NaiveDate,
+    in_date: Option<NaiveDate>,
+    class: ClassPreference,
+    currency: Currency,
 ) -> Result<(Vec<Train>, Vec<Train>), QueryError> {
     let text = match response {
-        Some(res) => res.text().await.map_err(QueryError::ReqwestError)?,
+        Some(res) => res,
         None => {
-            warn!("No trains found for {} and {} date pair", out_date, in_date);
+            warn_no_trains(out_date, in_date);
             return Ok((Vec::new(), Vec::new()));
         }
     };
@@ -299,28 +967,83 @@ async fn parse_response(
         }
     };
 
-    if json.outbound.is_none() || json.inbound.is_none() {
-        warn!("No trains found for {} and {} date pair", out_date, in_date);
+    if json.outbound.is_none() || (in_date.is_some() && json.inbound.is_none()) {
+        warn_no_trains(out_date, in_date);
     }
 
-    let out_trains = get_trains_from_res(json.outbound, out_date);
-    let in_trains = get_trains_from_res(json.inbound, in_date);
+    let out_trains = get_trains_from_res(json.outbound, out_date, class, currency);
+    let in_trains = match in_date {
+        Some(in_date) => get_trains_from_res(json.inbound, in_date, class, currency),
+        None => Vec::new(),
+    };
 
     Ok((out_trains, in_trains))
 }
 
-fn get_trains_from_res(in_or_out: Option<InOrOut>, date: NaiveDate) -> Vec<Train> {
+fn warn_no_trains(out_date: NaiveDate, in_date: Option<NaiveDate>) {
+    match in_date {
+        Some(in_date) => warn!("No trains found for {} and {} date pair", out_date, in_date),
+        None => warn!("No trains found for {}", out_date),
+    }
+}
+
+fn get_trains_from_res(
+    in_or_out: Option<InOrOut>,
+    date: NaiveDate,
+    class: ClassPreference,
+    currency: Currency,
+) -> Vec<Train> {
     let mut results = Vec::new();
     if in_or_out.is_none() {
         return results;
     }
 
     for train in in_or_out.unwrap().journey.iter() {
-        match &train.class[0].price {
-            Some(val) => {
+        let mut available = Vec::new();
+        for (i, train_class) in train.class.iter().enumerate() {
+            match (FareClass::from_index(i), &train_class.price) {
+                (Some(fare_class), Some(val)) if !train_class.is_not_available => {
+                    let fares = Fares {
+                        adult: Money::new(val.adult, currency),
+                        child: Money::new(val.child, currency),
+                        youth: Money::new(val.youth, currency),
+                        senior: Money::new(val.senior, currency),
+                    };
+                    available.push((
+                        fare_class,
+                        Money::new(val.adult + val.child + val.youth + val.senior, currency),
+                        fares,
+                        train_class.remaining,
+                    ));
+                }
+                _ => (),
+            }
+        }
+
+        let chosen = match class {
+            ClassPreference::Only(fare_class) => available
+                .into_iter()
+                .find(|(available_class, _, _, _)| *available_class == fare_class),
+            ClassPreference::AnyCheapest => available
+                .into_iter()
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap()),
+        };
+
+        match chosen {
+            Some((fare_class, price, fares, seats)) => {
+                let departure = NaiveDateTime::new(date, train.departure_time);
                 results.push(Train {
-                    price: val.adult,
-                    departure: NaiveDateTime::new(date, train.departure_time),
+                    price,
+                    fares,
+                    class: fare_class,
+                    seats,
+                    details: train.details.clone(),
+                    departure,
+                    arrival: date::get_arrival_datetime(
+                        departure,
+                        train.duration,
+                        train.arrival_time,
+                    ),
                     duration: train.duration,
                 });
             }
@@ -337,36 +1060,96 @@ fn format_date(date: NaiveDate) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::CacheConfig;
     use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
     use mockito::{mock, Matcher, Mock};
 
     static API_KEY: &str = "api-key";
     static FROM: i32 = 123;
     static TO: i32 = 321;
+    static RETURN_FROM: i32 = 456;
+    static STANDARD: ClassPreference = ClassPreference::Only(FareClass::Standard);
+
+    fn api() -> Api {
+        Api::new(
+            API_KEY,
+            &ClientConfig {
+                base_url: mockito::server_url(),
+                retry: RetryPolicy {
+                    max_attempts: 2,
+                    initial_backoff: std::time::Duration::from_millis(10),
+                    ..RetryPolicy::default()
+                },
+                ..ClientConfig::default()
+            },
+        )
+    }
+
+    fn gbp(amount: &str) -> Money {
+        Money::new(amount.parse().unwrap(), Currency::Gbp)
+    }
+
+    impl Fares {
+        fn adult(adult: &str) -> Fares {
+            Fares {
+                adult: gbp(adult),
+                ..Fares::zero(Currency::Gbp)
+            }
+        }
+    }
+
+    impl TrainDetails {
+        fn new(number: &str, direct: bool) -> TrainDetails {
+            TrainDetails {
+                number: number.to_string(),
+                direct,
+                customs_checks: false,
+                dob_required: false,
+            }
+        }
+    }
+
+    impl Passengers {
+        fn adults(adults: i16) -> Passengers {
+            Passengers {
+                adults,
+                children: 0,
+                youths: 0,
+                seniors: 0,
+            }
+        }
+    }
 
-    impl Filter {
-        fn new() -> Filter {
-            Filter {
-                max_price: None,
-                out_departure_after: None,
-                out_departure_before: None,
-                in_departure_before: None,
-                in_departure_after: None,
+    impl Itinerary {
+        fn new(from: i32, to: i32) -> Itinerary {
+            Itinerary {
+                from,
+                to,
+                return_from: to,
+                return_to: from,
             }
         }
     }
+
+    /// Error for the only date pair which was searched
+    fn single_failure<T: fmt::Debug>(results: SearchResults<T>) -> Arc<QueryError> {
+        assert!(results.results.is_empty(), "{:?}", results.results);
+        assert_eq!(results.failures.len(), 1, "{:?}", results.failures);
+        results.failures.into_iter().next().unwrap().error
+    }
+
     fn create_mock() -> (Vec<(NaiveDate, NaiveDate)>, Mock) {
         let dates = vec![(
-            NaiveDate::from_ymd(2020, 04, 05),
-            NaiveDate::from_ymd(2020, 04, 07),
+            NaiveDate::from_ymd(2020, 4, 5),
+            NaiveDate::from_ymd(2020, 4, 7),
         )];
         let mock = mock(
             "GET",
-            Matcher::Exact(format!("/{}/{}/{}", SEARCH_LOCATION, FROM, TO)),
+            Matcher::Exact(format!("/{}/uk-en/{}/{}", SEARCH_LOCATION, FROM, TO)),
         )
         .match_query(Matcher::AllOf(vec![
-            Matcher::UrlEncoded("outbound-date".into(), dates[0].0.to_string().into()),
-            Matcher::UrlEncoded("inbound-date".into(), dates[0].1.to_string().into()),
+            Matcher::UrlEncoded("outbound-date".into(), dates[0].0.to_string()),
+            Matcher::UrlEncoded("inbound-date".into(), dates[0].1.to_string()),
             Matcher::UrlEncoded("adult".into(), "2".into()),
         ]))
         .match_header("x-apikey", API_KEY)
@@ -381,22 +1164,38 @@ mod tests {
             .with_status(200)
             .with_body(include_str!("test_resources/response.json"))
             .create();
-        let ref mut filter1 = Filter::new();
-        filter1.max_price = Some(100.0);
+        let filter1 = &mut Filter::default();
+        filter1.max_price = Some(gbp("100"));
 
         // Max price set
-        let journeys = get_journeys(&dates, API_KEY, FROM, TO, 2, filter1)
-            .await
-            .unwrap();
+        let journeys = get_journeys(
+            &dates,
+            &api(),
+            &Itinerary::new(FROM, TO),
+            &Passengers::adults(2),
+            STANDARD,
+            filter1,
+        )
+        .await
+        .results;
 
         assert_eq!(
             journeys,
             vec![TrainJourney {
                 outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(5, 40, 0)),
                 inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(6, 33, 0)),
-                price: 78.5,
+                out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(9, 17, 0)),
+                in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 2, 0)),
+                price: gbp("78.5"),
+                fares: Fares::adult("78.5"),
                 out_duration: Duration::minutes(157),
                 in_duration: Duration::minutes(149),
+                out_class: FareClass::Standard,
+                in_class: FareClass::Standard,
+                out_seats: Some(109),
+                in_seats: Some(31),
+                out_details: TrainDetails::new("9080", true),
+                in_details: TrainDetails::new("9005", true),
             }]
         );
     }
@@ -408,12 +1207,19 @@ mod tests {
             .with_status(200)
             .with_body(include_str!("test_resources/response.json"))
             .create();
-        let ref filter = Filter::new();
+        let filter = &Filter::default();
 
         // Max price not set
-        let journeys = get_journeys(&dates, API_KEY, 123, 321, 2, filter)
-            .await
-            .unwrap();
+        let journeys = get_journeys(
+            &dates,
+            &api(),
+            &Itinerary::new(FROM, TO),
+            &Passengers::adults(2),
+            STANDARD,
+            filter,
+        )
+        .await
+        .results;
 
         assert_eq!(
             journeys,
@@ -421,65 +1227,146 @@ mod tests {
                 TrainJourney {
                     outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(5, 40, 0)),
                     inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(6, 33, 0)),
-                    price: 78.5,
+                    out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(9, 17, 0)),
+                    in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 2, 0)),
+                    price: gbp("78.5"),
+                    fares: Fares::adult("78.5"),
                     out_duration: Duration::minutes(157),
                     in_duration: Duration::minutes(149),
+                    out_class: FareClass::Standard,
+                    in_class: FareClass::Standard,
+                    out_seats: Some(109),
+                    in_seats: Some(31),
+                    out_details: TrainDetails::new("9080", true),
+                    in_details: TrainDetails::new("9005", true),
                 },
                 TrainJourney {
                     outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(5, 40, 0)),
                     inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 33, 0)),
-                    price: 128.5,
+                    out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(9, 17, 0)),
+                    in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(9, 2, 0)),
+                    price: gbp("128.5"),
+                    fares: Fares::adult("128.5"),
                     out_duration: Duration::minutes(157),
                     in_duration: Duration::minutes(149),
+                    out_class: FareClass::Standard,
+                    in_class: FareClass::Standard,
+                    out_seats: Some(109),
+                    in_seats: Some(31),
+                    out_details: TrainDetails::new("9080", true),
+                    in_details: TrainDetails::new("9006", true),
                 },
                 TrainJourney {
                     outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(5, 40, 0)),
                     inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 53, 0)),
-                    price: 128.5,
+                    out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(9, 17, 0)),
+                    in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(9, 22, 0)),
+                    price: gbp("128.5"),
+                    fares: Fares::adult("128.5"),
                     out_duration: Duration::minutes(157),
                     in_duration: Duration::minutes(149),
+                    out_class: FareClass::Standard,
+                    in_class: FareClass::Standard,
+                    out_seats: Some(109),
+                    in_seats: Some(31),
+                    out_details: TrainDetails::new("9080", true),
+                    in_details: TrainDetails::new("9007", true),
                 },
                 TrainJourney {
                     outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(6, 40, 0)),
                     inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(6, 33, 0)),
-                    price: 108.5,
+                    out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(11, 17, 0)),
+                    in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 2, 0)),
+                    price: gbp("108.5"),
+                    fares: Fares::adult("108.5"),
                     out_duration: Duration::minutes(133),
                     in_duration: Duration::minutes(149),
+                    out_class: FareClass::Standard,
+                    in_class: FareClass::Standard,
+                    out_seats: Some(50),
+                    in_seats: Some(31),
+                    out_details: TrainDetails::new("9081", true),
+                    in_details: TrainDetails::new("9005", true),
                 },
                 TrainJourney {
                     outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(6, 40, 0)),
                     inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 33, 0)),
-                    price: 158.5,
+                    out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(11, 17, 0)),
+                    in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(9, 2, 0)),
+                    price: gbp("158.5"),
+                    fares: Fares::adult("158.5"),
                     out_duration: Duration::minutes(133),
                     in_duration: Duration::minutes(149),
+                    out_class: FareClass::Standard,
+                    in_class: FareClass::Standard,
+                    out_seats: Some(50),
+                    in_seats: Some(31),
+                    out_details: TrainDetails::new("9081", true),
+                    in_details: TrainDetails::new("9006", true),
                 },
                 TrainJourney {
                     outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(6, 40, 0)),
                     inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 53, 0)),
-                    price: 158.5,
+                    out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(11, 17, 0)),
+                    in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(9, 22, 0)),
+                    price: gbp("158.5"),
+                    fares: Fares::adult("158.5"),
                     out_duration: Duration::minutes(133),
                     in_duration: Duration::minutes(149),
+                    out_class: FareClass::Standard,
+                    in_class: FareClass::Standard,
+                    out_seats: Some(50),
+                    in_seats: Some(31),
+                    out_details: TrainDetails::new("9081", true),
+                    in_details: TrainDetails::new("9007", true),
                 },
                 TrainJourney {
                     outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(7, 40, 0)),
                     inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(6, 33, 0)),
-                    price: 128.5,
+                    out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(12, 17, 0)),
+                    in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 2, 0)),
+                    price: gbp("128.5"),
+                    fares: Fares::adult("128.5"),
                     out_duration: Duration::minutes(133),
                     in_duration: Duration::minutes(149),
+                    out_class: FareClass::Standard,
+                    in_class: FareClass::Standard,
+                    out_seats: Some(50),
+                    in_seats: Some(31),
+                    out_details: TrainDetails::new("9082", true),
+                    in_details: TrainDetails::new("9005", true),
                 },
                 TrainJourney {
                     outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(7, 40, 0)),
                     inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 33, 0)),
-                    price: 178.5,
+                    out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(12, 17, 0)),
+                    in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(9, 2, 0)),
+                    price: gbp("178.5"),
+                    fares: Fares::adult("178.5"),
                     out_duration: Duration::minutes(133),
                     in_duration: Duration::minutes(149),
+                    out_class: FareClass::Standard,
+                    in_class: FareClass::Standard,
+                    out_seats: Some(50),
+                    in_seats: Some(31),
+                    out_details: TrainDetails::new("9082", true),
+                    in_details: TrainDetails::new("9006", true),
                 },
                 TrainJourney {
                     outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(7, 40, 0)),
                     inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 53, 0)),
-                    price: 178.5,
+                    out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(12, 17, 0)),
+                    in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(9, 22, 0)),
+                    price: gbp("178.5"),
+                    fares: Fares::adult("178.5"),
                     out_duration: Duration::minutes(133),
                     in_duration: Duration::minutes(149),
+                    out_class: FareClass::Standard,
+                    in_class: FareClass::Standard,
+                    out_seats: Some(50),
+                    in_seats: Some(31),
+                    out_details: TrainDetails::new("9082", true),
+                    in_details: TrainDetails::new("9007", true),
                 },
             ]
         );
@@ -492,29 +1379,79 @@ mod tests {
             .with_status(200)
             .with_body(include_str!("test_resources/response.json"))
             .create();
-        let ref mut filter = Filter::new();
+        let filter = &mut Filter::default();
         filter.out_departure_after = Some(NaiveTime::from_hms(6, 0, 0));
         filter.out_departure_before = Some(NaiveTime::from_hms(7, 0, 0));
         filter.in_departure_after = Some(NaiveTime::from_hms(8, 0, 0));
         filter.in_departure_before = Some(NaiveTime::from_hms(8, 52, 0));
 
         // Departure after set
-        let journeys = get_journeys(&dates, API_KEY, 123, 321, 2, filter)
-            .await
-            .unwrap();
+        let journeys = get_journeys(
+            &dates,
+            &api(),
+            &Itinerary::new(FROM, TO),
+            &Passengers::adults(2),
+            STANDARD,
+            filter,
+        )
+        .await
+        .results;
 
         assert_eq!(
             journeys,
             vec![TrainJourney {
                 outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(6, 40, 0)),
                 inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 33, 0)),
-                price: 158.5,
+                out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(11, 17, 0)),
+                in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(9, 2, 0)),
+                price: gbp("158.5"),
+                fares: Fares::adult("158.5"),
                 out_duration: Duration::minutes(133),
                 in_duration: Duration::minutes(149),
+                out_class: FareClass::Standard,
+                in_class: FareClass::Standard,
+                out_seats: Some(50),
+                in_seats: Some(31),
+                out_details: TrainDetails::new("9081", true),
+                in_details: TrainDetails::new("9006", true),
             }]
         );
     }
 
+    #[tokio::test]
+    async fn test_get_journeys_filtered_by_arrival() {
+        let (dates, mock) = create_mock();
+        let _mock = mock
+            .with_status(200)
+            .with_body(include_str!("test_resources/response.json"))
+            .create();
+        let filter = &mut Filter::default();
+        filter.out_arrival_before = Some(NaiveTime::from_hms(10, 0, 0));
+        filter.in_arrival_after = Some(NaiveTime::from_hms(9, 0, 0));
+
+        let journeys = get_journeys(
+            &dates,
+            &api(),
+            &Itinerary::new(FROM, TO),
+            &Passengers::adults(2),
+            STANDARD,
+            filter,
+        )
+        .await
+        .results;
+
+        assert_eq!(
+            journeys
+                .iter()
+                .map(|journey| (journey.out_arrival.time(), journey.in_arrival.time()))
+                .collect::<Vec<(NaiveTime, NaiveTime)>>(),
+            vec![
+                (NaiveTime::from_hms(9, 17, 0), NaiveTime::from_hms(9, 2, 0)),
+                (NaiveTime::from_hms(9, 17, 0), NaiveTime::from_hms(9, 22, 0)),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_get_journeys_all_filters() {
         let (dates, mock) = create_mock();
@@ -522,39 +1459,603 @@ mod tests {
             .with_status(200)
             .with_body(include_str!("test_resources/response.json"))
             .create();
-        let ref filter = Filter {
-            max_price: Some(100.0),
+        let filter = &Filter {
+            max_price: Some(gbp("100")),
             out_departure_after: Some(NaiveTime::from_hms(5, 0, 0)),
             out_departure_before: Some(NaiveTime::from_hms(7, 0, 0)),
             in_departure_after: Some(NaiveTime::from_hms(6, 0, 0)),
             in_departure_before: Some(NaiveTime::from_hms(8, 30, 0)),
+            out_arrival_after: None,
+            out_arrival_before: None,
+            in_arrival_after: None,
+            in_arrival_before: None,
+            min_seats: None,
+            direct_only: false,
         };
 
         // Departure after set
-        let journeys = get_journeys(&dates, API_KEY, 123, 321, 2, filter)
-            .await
-            .unwrap();
+        let journeys = get_journeys(
+            &dates,
+            &api(),
+            &Itinerary::new(FROM, TO),
+            &Passengers::adults(2),
+            STANDARD,
+            filter,
+        )
+        .await
+        .results;
 
         assert_eq!(
             journeys,
             vec![TrainJourney {
                 outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(5, 40, 0)),
                 inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(6, 33, 0)),
-                price: 78.5,
+                out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(9, 17, 0)),
+                in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 2, 0)),
+                price: gbp("78.5"),
+                fares: Fares::adult("78.5"),
                 out_duration: Duration::minutes(157),
                 in_duration: Duration::minutes(149),
+                out_class: FareClass::Standard,
+                in_class: FareClass::Standard,
+                out_seats: Some(109),
+                in_seats: Some(31),
+                out_details: TrainDetails::new("9080", true),
+                in_details: TrainDetails::new("9005", true),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_one_way_trains() {
+        let date = NaiveDate::from_ymd(2020, 4, 5);
+        let _mock = mock(
+            "GET",
+            Matcher::Exact(format!("/{}/uk-en/{}/{}", SEARCH_LOCATION, FROM, TO)),
+        )
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("outbound-date".into(), date.to_string()),
+            Matcher::UrlEncoded("adult".into(), "2".into()),
+        ]))
+        .match_header("x-apikey", API_KEY)
+        .with_header("content-type", "application/json")
+        .with_status(200)
+        .with_body(include_str!("test_resources/response.json"))
+        .create();
+        let filter = &mut Filter::default();
+        filter.max_price = Some(gbp("60"));
+        filter.out_departure_after = Some(NaiveTime::from_hms(6, 0, 0));
+
+        let trains = get_one_way_trains(
+            &[date],
+            &api(),
+            FROM,
+            TO,
+            &Passengers::adults(2),
+            STANDARD,
+            filter,
+        )
+        .await
+        .results;
+
+        assert_eq!(
+            trains,
+            vec![Train {
+                departure: NaiveDateTime::new(date, NaiveTime::from_hms(6, 40, 0)),
+                arrival: NaiveDateTime::new(date, NaiveTime::from_hms(11, 17, 0)),
+                duration: Duration::minutes(133),
+                price: gbp("59"),
+                fares: Fares::adult("59"),
+                class: FareClass::Standard,
+                seats: Some(50),
+                details: TrainDetails::new("9081", true),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_one_way_trains_premier() {
+        let date = NaiveDate::from_ymd(2020, 4, 5);
+        let _mock = mock(
+            "GET",
+            Matcher::Exact(format!("/{}/uk-en/{}/{}", SEARCH_LOCATION, FROM, TO)),
+        )
+        .match_query(Matcher::UrlEncoded(
+            "outbound-date".into(),
+            date.to_string(),
+        ))
+        .with_status(200)
+        .with_body(include_str!("test_resources/response.json"))
+        .create();
+
+        let trains = get_one_way_trains(
+            &[date],
+            &api(),
+            FROM,
+            TO,
+            &Passengers::adults(2),
+            ClassPreference::Only(FareClass::Premier),
+            &Filter::default(),
+        )
+        .await
+        .results;
+
+        // Only the first train has Premier class available
+        assert_eq!(
+            trains,
+            vec![Train {
+                departure: NaiveDateTime::new(date, NaiveTime::from_hms(5, 40, 0)),
+                arrival: NaiveDateTime::new(date, NaiveTime::from_hms(9, 17, 0)),
+                duration: Duration::minutes(157),
+                price: gbp("245"),
+                fares: Fares::adult("245"),
+                class: FareClass::Premier,
+                seats: Some(105),
+                details: TrainDetails::new("9080", true),
             }]
         );
     }
+
+    #[tokio::test]
+    async fn test_get_one_way_trains_min_seats() {
+        let date = NaiveDate::from_ymd(2020, 4, 5);
+        let _mock = mock(
+            "GET",
+            Matcher::Exact(format!("/{}/uk-en/{}/{}", SEARCH_LOCATION, FROM, TO)),
+        )
+        .match_query(Matcher::UrlEncoded(
+            "outbound-date".into(),
+            date.to_string(),
+        ))
+        .with_status(200)
+        .with_body(include_str!("test_resources/response.json"))
+        .create();
+        let filter = &mut Filter::default();
+        filter.min_seats = Some(60);
+
+        let trains = get_one_way_trains(
+            &[date],
+            &api(),
+            FROM,
+            TO,
+            &Passengers::adults(2),
+            STANDARD,
+            filter,
+        )
+        .await
+        .results;
+
+        assert_eq!(
+            trains,
+            vec![Train {
+                departure: NaiveDateTime::new(date, NaiveTime::from_hms(5, 40, 0)),
+                arrival: NaiveDateTime::new(date, NaiveTime::from_hms(9, 17, 0)),
+                duration: Duration::minutes(157),
+                price: gbp("29"),
+                fares: Fares::adult("29"),
+                class: FareClass::Standard,
+                seats: Some(109),
+                details: TrainDetails::new("9080", true),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_one_way_trains_direct_only() {
+        let date = NaiveDate::from_ymd(2020, 4, 5);
+        let _mock = mock(
+            "GET",
+            Matcher::Exact(format!("/{}/uk-en/{}/{}", SEARCH_LOCATION, FROM, TO)),
+        )
+        .match_query(Matcher::UrlEncoded(
+            "outbound-date".into(),
+            date.to_string(),
+        ))
+        .with_status(200)
+        .with_body(include_str!("test_resources/response_connection.json"))
+        .create();
+        let filter = &mut Filter::default();
+        filter.direct_only = true;
+
+        let trains = get_one_way_trains(
+            &[date],
+            &api(),
+            FROM,
+            TO,
+            &Passengers::adults(2),
+            STANDARD,
+            filter,
+        )
+        .await
+        .results;
+
+        // 9082 goes via Lille
+        assert_eq!(
+            trains
+                .iter()
+                .map(|train| train.details.number.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["9080"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_journeys_deduplicates_requests() {
+        let dates = vec![
+            (
+                NaiveDate::from_ymd(2020, 4, 5),
+                NaiveDate::from_ymd(2020, 4, 7),
+            ),
+            (
+                NaiveDate::from_ymd(2020, 4, 6),
+                NaiveDate::from_ymd(2020, 4, 7),
+            ),
+        ];
+        let combined = mock(
+            "GET",
+            Matcher::Exact(format!("/{}/uk-en/{}/{}", SEARCH_LOCATION, FROM, TO)),
+        )
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("outbound-date".into(), dates[0].0.to_string()),
+            Matcher::UrlEncoded("inbound-date".into(), dates[0].1.to_string()),
+        ]))
+        .with_status(200)
+        .with_body(include_str!("test_resources/response.json"))
+        .expect(1)
+        .create();
+        let outbound = mock(
+            "GET",
+            Matcher::Exact(format!("/{}/uk-en/{}/{}", SEARCH_LOCATION, FROM, TO)),
+        )
+        .match_query(Matcher::UrlEncoded(
+            "outbound-date".into(),
+            dates[1].0.to_string(),
+        ))
+        .with_status(200)
+        .with_body(include_str!("test_resources/response.json"))
+        .expect(1)
+        .create();
+
+        let journeys = get_journeys(
+            &dates,
+            &api(),
+            &Itinerary::new(FROM, TO),
+            &Passengers::adults(2),
+            STANDARD,
+            &Filter::default(),
+        )
+        .await
+        .results;
+
+        combined.assert();
+        outbound.assert();
+        let mut found: Vec<(NaiveDate, NaiveDate)> = journeys
+            .iter()
+            .map(|journey| (journey.outbound.date(), journey.inbound.date()))
+            .collect();
+        found.dedup();
+        assert_eq!(found, dates);
+    }
+
+    #[tokio::test]
+    async fn test_get_journeys_partial_results() {
+        let dates = vec![
+            (
+                NaiveDate::from_ymd(2020, 4, 5),
+                NaiveDate::from_ymd(2020, 4, 7),
+            ),
+            (
+                NaiveDate::from_ymd(2020, 4, 6),
+                NaiveDate::from_ymd(2020, 4, 8),
+            ),
+        ];
+        let _mocks: Vec<Mock> = [(dates[0], 200), (dates[1], 404)]
+            .iter()
+            .map(|((outbound_date, inbound_date), status)| {
+                mock(
+                    "GET",
+                    Matcher::Exact(format!("/{}/uk-en/{}/{}", SEARCH_LOCATION, FROM, TO)),
+                )
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("outbound-date".into(), outbound_date.to_string()),
+                    Matcher::UrlEncoded("inbound-date".into(), inbound_date.to_string()),
+                ]))
+                .with_status(*status)
+                .with_body(include_str!("test_resources/response.json"))
+                .create()
+            })
+            .collect();
+
+        let journeys = get_journeys(
+            &dates,
+            &api(),
+            &Itinerary::new(FROM, TO),
+            &Passengers::adults(2),
+            STANDARD,
+            &Filter::default(),
+        )
+        .await;
+
+        assert!(!journeys.results.is_empty());
+        assert!(journeys
+            .results
+            .iter()
+            .all(|journey| journey.outbound.date() == dates[0].0));
+        assert_eq!(journeys.failures.len(), 1);
+        assert_eq!(journeys.failures[0].outbound, dates[1].0);
+        assert_eq!(journeys.failures[0].inbound, Some(dates[1].1));
+    }
+
+    #[test]
+    fn test_plan_requests() {
+        let day = |day| NaiveDate::from_ymd(2020, 4, day);
+        let travels = vec![
+            (day(1), day(2)),
+            (day(1), day(3)),
+            (day(5), day(6)),
+            (day(7), day(9)),
+            (day(8), day(9)),
+        ];
+
+        assert_eq!(
+            plan_requests(&travels, true),
+            RequestPlan {
+                combined: vec![(day(1), day(2)), (day(5), day(6)), (day(7), day(9))],
+                outbound: vec![day(8)],
+                inbound: vec![day(3)],
+            }
+        );
+        assert_eq!(
+            plan_requests(&travels, false),
+            RequestPlan {
+                combined: Vec::new(),
+                outbound: vec![day(1), day(5), day(7), day(8)],
+                inbound: vec![day(2), day(3), day(6), day(9)],
+            }
+        );
+    }
+
+    #[test]
+    fn test_class_preference_from_str() {
+        assert_eq!(
+            "Standard".parse::<ClassPreference>().unwrap(),
+            ClassPreference::Only(FareClass::Standard)
+        );
+        assert_eq!(
+            "plus".parse::<ClassPreference>().unwrap(),
+            ClassPreference::Only(FareClass::Plus)
+        );
+        assert_eq!(
+            "any-cheapest".parse::<ClassPreference>().unwrap(),
+            ClassPreference::AnyCheapest
+        );
+        assert!("business".parse::<ClassPreference>().is_err());
+
+        for class in ["standard", "premier", "any-cheapest"].iter() {
+            assert_eq!(
+                class.parse::<ClassPreference>().unwrap().to_string(),
+                *class
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_journeys_open_jaw() {
+        let dates = vec![(
+            NaiveDate::from_ymd(2020, 4, 5),
+            NaiveDate::from_ymd(2020, 4, 7),
+        )];
+        let mocks: Vec<Mock> = [(FROM, TO, dates[0].0), (RETURN_FROM, FROM, dates[0].1)]
+            .iter()
+            .map(|(from, to, date)| {
+                mock(
+                    "GET",
+                    Matcher::Exact(format!("/{}/uk-en/{}/{}", SEARCH_LOCATION, from, to)),
+                )
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("outbound-date".into(), date.to_string()),
+                    Matcher::UrlEncoded("adult".into(), "2".into()),
+                ]))
+                .match_header("x-apikey", API_KEY)
+                .with_header("content-type", "application/json")
+                .with_status(200)
+                .with_body(include_str!("test_resources/response.json"))
+                .create()
+            })
+            .collect();
+        let itinerary = &Itinerary {
+            from: FROM,
+            to: TO,
+            return_from: RETURN_FROM,
+            return_to: FROM,
+        };
+        let filter = &mut Filter::default();
+        filter.max_price = Some(gbp("60"));
+
+        let journeys = get_journeys(
+            &dates,
+            &api(),
+            itinerary,
+            &Passengers::adults(2),
+            STANDARD,
+            filter,
+        )
+        .await
+        .results;
+
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+        assert_eq!(
+            journeys,
+            vec![TrainJourney {
+                outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(5, 40, 0)),
+                inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(5, 40, 0)),
+                out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(9, 17, 0)),
+                in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(9, 17, 0)),
+                price: gbp("58"),
+                fares: Fares::adult("58"),
+                out_duration: Duration::minutes(157),
+                in_duration: Duration::minutes(157),
+                out_class: FareClass::Standard,
+                in_class: FareClass::Standard,
+                out_seats: Some(109),
+                in_seats: Some(109),
+                out_details: TrainDetails::new("9080", true),
+                in_details: TrainDetails::new("9080", true),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_journeys_passenger_mix() {
+        let dates = vec![(
+            NaiveDate::from_ymd(2020, 4, 5),
+            NaiveDate::from_ymd(2020, 4, 7),
+        )];
+        let _mock = mock(
+            "GET",
+            Matcher::Exact(format!("/{}/uk-en/{}/{}", SEARCH_LOCATION, FROM, TO)),
+        )
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("outbound-date".into(), dates[0].0.to_string()),
+            Matcher::UrlEncoded("inbound-date".into(), dates[0].1.to_string()),
+            Matcher::UrlEncoded("adult".into(), "2".into()),
+            Matcher::UrlEncoded("child".into(), "1".into()),
+            Matcher::UrlEncoded("senior".into(), "1".into()),
+        ]))
+        .match_header("x-apikey", API_KEY)
+        .with_header("content-type", "application/json")
+        .with_status(200)
+        .with_body(include_str!("test_resources/response_passengers.json"))
+        .create();
+        let passengers = &Passengers {
+            adults: 2,
+            children: 1,
+            youths: 0,
+            seniors: 1,
+        };
+
+        let journeys = get_journeys(
+            &dates,
+            &api(),
+            &Itinerary::new(FROM, TO),
+            passengers,
+            STANDARD,
+            &Filter::default(),
+        )
+        .await
+        .results;
+
+        assert_eq!(
+            journeys,
+            vec![TrainJourney {
+                outbound: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(5, 40, 0)),
+                inbound: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(6, 33, 0)),
+                out_arrival: NaiveDateTime::new(dates[0].0, NaiveTime::from_hms(9, 17, 0)),
+                in_arrival: NaiveDateTime::new(dates[0].1, NaiveTime::from_hms(8, 2, 0)),
+                price: gbp("215"),
+                fares: Fares {
+                    adult: gbp("90"),
+                    child: gbp("45"),
+                    youth: gbp("0"),
+                    senior: gbp("80"),
+                },
+                out_duration: Duration::minutes(157),
+                in_duration: Duration::minutes(149),
+                out_class: FareClass::Standard,
+                in_class: FareClass::Standard,
+                out_seats: Some(109),
+                in_seats: Some(31),
+                out_details: TrainDetails::new("9080", true),
+                in_details: TrainDetails::new("9005", true),
+            }]
+        );
+        assert_eq!(
+            passengers.per_person(&journeys[0].fares),
+            Fares {
+                adult: gbp("45"),
+                child: gbp("45"),
+                youth: gbp("0"),
+                senior: gbp("80"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_train_journey_stay_length() {
+        let journey = TrainJourney {
+            outbound: NaiveDateTime::new(
+                NaiveDate::from_ymd(2020, 4, 3),
+                NaiveTime::from_hms(18, 1, 0),
+            ),
+            inbound: NaiveDateTime::new(
+                NaiveDate::from_ymd(2020, 4, 5),
+                NaiveTime::from_hms(20, 33, 0),
+            ),
+            out_arrival: NaiveDateTime::new(
+                NaiveDate::from_ymd(2020, 4, 3),
+                NaiveTime::from_hms(21, 17, 0),
+            ),
+            in_arrival: NaiveDateTime::new(
+                NaiveDate::from_ymd(2020, 4, 5),
+                NaiveTime::from_hms(21, 49, 0),
+            ),
+            price: gbp("58"),
+            fares: Fares::adult("58"),
+            out_duration: Duration::minutes(136),
+            in_duration: Duration::minutes(136),
+            out_class: FareClass::Standard,
+            in_class: FareClass::Standard,
+            out_seats: Some(50),
+            in_seats: Some(31),
+            out_details: TrainDetails::new("9080", true),
+            in_details: TrainDetails::new("9005", true),
+        };
+
+        assert_eq!(journey.stay_length(), 3);
+    }
+
+    #[test]
+    fn test_parse_response_prices() {
+        let date = NaiveDate::from_ymd(2020, 4, 5);
+        let body = r#"{"outbound": {"journey": [{
+            "id": "9004", "departureTime": "09:01", "arrivalTime": "12:17", "duration": 136,
+            "class": [{"remaining": 10, "price": {"adult": 39.99, "child": 19.9}}]
+        }]}}"#;
+        let passengers = Passengers {
+            adults: 3,
+            children: 1,
+            youths: 0,
+            seniors: 0,
+        };
+
+        let (trains, _) =
+            parse_response(Some(body.to_string()), date, None, STANDARD, Currency::Eur).unwrap();
+
+        let eur = |amount: &str| Money::new(amount.parse().unwrap(), Currency::Eur);
+        assert_eq!(trains[0].price, eur("59.89"));
+        assert_eq!(trains[0].fares.adult, eur("39.99"));
+        assert_eq!(trains[0].fares.youth, eur("0"));
+        assert_eq!(passengers.per_person(&trains[0].fares).adult, eur("13.33"));
+    }
+
     #[tokio::test]
     async fn test_empty_response() {
         let (dates, mock) = create_mock();
         let _mock = mock.with_status(200).with_body("{}").create();
-        let ref filter = Filter::new();
-
-        let journeys = get_journeys(&dates, API_KEY, 123, 321, 2, filter)
-            .await
-            .unwrap();
+        let filter = &Filter::default();
+
+        let journeys = get_journeys(
+            &dates,
+            &api(),
+            &Itinerary::new(FROM, TO),
+            &Passengers::adults(2),
+            STANDARD,
+            filter,
+        )
+        .await
+        .results;
 
         assert_eq!(journeys, vec![]);
     }
@@ -562,15 +2063,34 @@ mod tests {
     #[tokio::test]
     async fn test_get_journeys_500_response() {
         let (dates, mock) = create_mock();
-        let _mock = mock.with_status(500).with_body("server crashed").create();
-        let ref filter = Filter::new();
-
-        assert_eq!(
-            Vec::<TrainJourney>::new(),
-            get_journeys(&dates, API_KEY, FROM, TO, 2, filter)
-                .await
-                .unwrap(),
-        )
+        let mock = mock
+            .with_status(500)
+            .with_body("server crashed")
+            .expect(2)
+            .create();
+        let filter = &Filter::default();
+
+        match &*single_failure(
+            get_journeys(
+                &dates,
+                &api(),
+                &Itinerary::new(FROM, TO),
+                &Passengers::adults(2),
+                STANDARD,
+                filter,
+            )
+            .await,
+        ) {
+            QueryError::ServerError(err) => {
+                assert!(err.starts_with("Got 500 Internal Server Error"));
+            }
+            default => panic!(
+                "get_journeys return {:?}, it should return QueryError::ServerError!",
+                default
+            ),
+        }
+        // Retried once before giving up
+        mock.assert();
     }
 
     #[tokio::test]
@@ -580,13 +2100,20 @@ mod tests {
             .with_status(422)
             .with_body("no entities found")
             .create();
-        let ref filter = Filter::new();
+        let filter = &Filter::default();
 
         assert_eq!(
             Vec::<TrainJourney>::new(),
-            get_journeys(&dates, API_KEY, FROM, TO, 2, filter)
-                .await
-                .unwrap(),
+            get_journeys(
+                &dates,
+                &api(),
+                &Itinerary::new(FROM, TO),
+                &Passengers::adults(2),
+                STANDARD,
+                filter
+            )
+            .await
+            .results,
         )
     }
 
@@ -594,10 +2121,20 @@ mod tests {
     async fn test_get_journeys_404_response() {
         let (dates, mock) = create_mock();
         let _mock = mock.with_status(404).with_body("never existed").create();
-        let ref filter = Filter::new();
-
-        match get_journeys(&dates, API_KEY, FROM, TO, 2, filter).await {
-            Err(QueryError::InternalError(err)) => {
+        let filter = &Filter::default();
+
+        match &*single_failure(
+            get_journeys(
+                &dates,
+                &api(),
+                &Itinerary::new(FROM, TO),
+                &Passengers::adults(2),
+                STANDARD,
+                filter,
+            )
+            .await,
+        ) {
+            QueryError::InternalError(err) => {
                 assert!(err.starts_with("Got 404 Not Found"));
             }
             default => panic!(
@@ -611,10 +2148,20 @@ mod tests {
     async fn test_get_journeys_invalid_json() {
         let (dates, mock) = create_mock();
         let _mock = mock.with_status(200).with_body("not a json").create();
-        let ref filter = Filter::new();
-
-        match get_journeys(&dates, API_KEY, FROM, TO, 2, filter).await {
-            Err(QueryError::JsonParseError(err)) => assert_eq!(
+        let filter = &Filter::default();
+
+        match &*single_failure(
+            get_journeys(
+                &dates,
+                &api(),
+                &Itinerary::new(FROM, TO),
+                &Passengers::adults(2),
+                STANDARD,
+                filter,
+            )
+            .await,
+        ) {
+            QueryError::JsonParseError(err) => assert_eq!(
                 err,
                 "Error while parsing JSON: Error(\"expected ident\", line: 1, column: 2)"
             ),
@@ -627,13 +2174,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_stations_map_ok() {
-        let _mock = mock("GET", format!("/{}", STATIONS_LOCATION).as_str())
+        let _mock = mock("GET", format!("/{}/uk-en", STATIONS_LOCATION).as_str())
             .with_header(API_KEY_HEADER, API_KEY)
             .with_body(include_str!("test_resources/stations.json"))
             .with_status(200)
             .create();
 
-        let stations = get_stations_map(API_KEY).await.unwrap();
+        let stations = get_stations_map(&api()).await.unwrap();
 
         assert_eq!(
             stations,
@@ -641,15 +2188,41 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_get_stations_map_cached() {
+        let dir = std::env::temp_dir().join(format!("eurostarchecker-api-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut config = CacheConfig::in_user_cache_dir().unwrap();
+        config.dir = dir;
+        let api = Api::new(
+            API_KEY,
+            &ClientConfig {
+                base_url: mockito::server_url(),
+                cache: Some(config),
+                ..ClientConfig::default()
+            },
+        );
+        let mock = mock("GET", format!("/{}/uk-en", STATIONS_LOCATION).as_str())
+            .with_body(include_str!("test_resources/stations.json"))
+            .with_status(200)
+            .expect(1)
+            .create();
+
+        let stations = get_stations_map(&api).await.unwrap();
+
+        assert_eq!(get_stations_map(&api).await.unwrap(), stations);
+        mock.assert();
+    }
+
     #[tokio::test]
     async fn test_get_stations_map_invalid_json() -> Result<(), String> {
-        let _mock = mock("GET", format!("/{}", STATIONS_LOCATION).as_str())
+        let _mock = mock("GET", format!("/{}/uk-en", STATIONS_LOCATION).as_str())
             .with_header(API_KEY_HEADER, API_KEY)
             .with_body("{foo")
             .with_status(200)
             .create();
 
-        match get_stations_map(API_KEY).await {
+        match get_stations_map(&api()).await {
             Err(QueryError::JsonParseError(_)) => Ok(()),
             default => Err(format!(
                 "get_stations_map returned: {:?} should return JsonParseError!",
@@ -660,13 +2233,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_stations_map_empty_json() -> Result<(), String> {
-        let _mock = mock("GET", format!("/{}", STATIONS_LOCATION).as_str())
+        let _mock = mock("GET", format!("/{}/uk-en", STATIONS_LOCATION).as_str())
             .with_header(API_KEY_HEADER, API_KEY)
             .with_body("{}")
             .with_status(200)
             .create();
 
-        match get_stations_map(API_KEY).await {
+        match get_stations_map(&api()).await {
             Err(QueryError::InternalError(_)) => Ok(()),
             default => Err(format!(
                 "get_stations_map returned: {:?} should return InternalError!",